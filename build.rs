@@ -0,0 +1,19 @@
+//! Records the version of the compiler building Overtone, so plugins can be checked
+//! against the host they are loaded into (see `overtone::plugin::PluginFingerprint`).
+
+use std::env;
+use std::process::Command;
+
+fn main() {
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let version = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|version| version.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    println!("cargo:rustc-env=OVERTONE_RUSTC_VERSION={version}");
+    println!("cargo:rerun-if-changed=build.rs");
+}
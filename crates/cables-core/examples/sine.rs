@@ -1,5 +1,5 @@
 use std::ptr::NonNull;
-use cables_core::graph::{Graph, Node, SocketData};
+use cables_core::graph::{Graph, Node, ProcessContext, SocketData};
 use cables_core::as_output;
 
 fn main() {
@@ -7,17 +7,17 @@ fn main() {
 
     let sine = graph.insert(SineNode { frequency: 440.0 });
 
-    let mut pip = graph.compile(sine, 0);
-    pip.run();
+    let mut pip = graph.compile(sine, 0).unwrap();
+    pip.run(&ProcessContext::default());
 }
 
 struct SineNode {
     frequency: f32,
 }
 impl Node for SineNode {
-    fn bind_parameters<'pip>(&self, parameters: &mut dyn Iterator<Item=NonNull<u8>>) -> Box<dyn FnMut() + 'pip> {
+    fn bind_parameters<'pip>(&self, parameters: &mut dyn Iterator<Item=NonNull<u8>>) -> Box<dyn FnMut(&ProcessContext) + Send + 'pip> {
         let out = as_output::<f64>(parameters.next().unwrap());
-        Box::new(move || {
+        Box::new(move |_| {
             *out = 42.0
         })
     }
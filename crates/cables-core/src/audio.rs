@@ -0,0 +1,62 @@
+use std::ops::{Deref, DerefMut};
+use crate::graph::ProcessContext;
+
+/// How many frames an [`AudioBlock`] holds, unless told otherwise.
+pub const DEFAULT_BLOCK_LEN: usize = 64;
+
+/// A fixed-size block of mono audio, for nodes that process many frames per run.
+///
+/// Only the first [`ProcessContext::block_len`] frames of the block are meaningful
+/// during a run — see [`AudioBlock::frames`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AudioBlock<const N: usize = DEFAULT_BLOCK_LEN>(pub [f32; N]);
+
+impl<const N: usize> AudioBlock<N> {
+    /// The frames of this block that are in use during the current run.
+    ///
+    /// # Panics
+    ///
+    /// If the run is longer than the block ([`ProcessContext::block_len`] is over `N`),
+    /// as the frames that don't fit would be lost.
+    pub fn frames(&self, context: &ProcessContext) -> &[f32] {
+        &self.0[..Self::len(context)]
+    }
+
+    /// The frames of this block that are in use during the current run.
+    ///
+    /// # Panics
+    ///
+    /// Like [`AudioBlock::frames`], if the run is longer than the block.
+    pub fn frames_mut(&mut self, context: &ProcessContext) -> &mut [f32] {
+        &mut self.0[..Self::len(context)]
+    }
+
+    fn len(context: &ProcessContext) -> usize {
+        assert!(
+            context.block_len <= N,
+            "runs of {} frames don't fit in blocks of {N}",
+            context.block_len
+        );
+        context.block_len
+    }
+}
+
+impl<const N: usize> Default for AudioBlock<N> {
+    fn default() -> Self {
+        Self([0.0; N])
+    }
+}
+
+impl<const N: usize> Deref for AudioBlock<N> {
+    type Target = [f32; N];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<const N: usize> DerefMut for AudioBlock<N> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
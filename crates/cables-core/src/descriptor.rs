@@ -0,0 +1,101 @@
+//! # Node Descriptors
+//!
+//! What a graph editor shows about a node: its name, where to file it in an "add node"
+//! menu, and what its sockets and parameters are. Every [`Node`] can describe itself
+//! (see [`Node::descriptor`]), and a [`NodeRegistry`](crate::registry::NodeRegistry)
+//! lists the descriptors of every type it knows.
+
+use crate::graph::{Node, SocketData};
+use crate::registry::ParameterSchema;
+
+/// Describes a kind of node to a user interface.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeDescriptor {
+    /// The id the node's type is registered under, if it was registered.
+    pub type_id: Option<&'static str>,
+    /// What the node is called in a user interface.
+    pub name: &'static str,
+    /// Where the node is filed in an "add node" menu.
+    pub category: &'static str,
+    /// A sentence or two about what the node does.
+    pub description: &'static str,
+    pub inputs: Vec<SocketDescriptor>,
+    pub outputs: Vec<SocketDescriptor>,
+    /// The parameters the node's type is built from, if it was registered.
+    pub parameters: Vec<ParameterSchema>,
+}
+
+/// The category of nodes that don't say which one they belong to.
+pub const UNCATEGORIZED: &str = "Uncategorized";
+
+impl NodeDescriptor {
+    /// Describes a node's sockets, naming it after its type.
+    pub fn of<N: Node + ?Sized>(node: &N) -> Self {
+        let inputs = (0..)
+            .map_while(|index| node.input_socket(index))
+            .map(|socket| SocketDescriptor::from(&socket))
+            .collect();
+        let outputs = (0..)
+            .map_while(|index| node.output_socket(index))
+            .map(|socket| SocketDescriptor::from(&socket))
+            .collect();
+
+        Self {
+            type_id: None,
+            name: short_type_name(std::any::type_name_of_val(node)),
+            category: UNCATEGORIZED,
+            description: "",
+            inputs,
+            outputs,
+            parameters: Vec::new(),
+        }
+    }
+
+    pub fn named(mut self, name: &'static str) -> Self {
+        self.name = name;
+        self
+    }
+
+    pub fn in_category(mut self, category: &'static str) -> Self {
+        self.category = category;
+        self
+    }
+
+    pub fn with_description(mut self, description: &'static str) -> Self {
+        self.description = description;
+        self
+    }
+}
+
+/// Describes one socket of a node to a user interface.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SocketDescriptor {
+    pub name: Option<&'static str>,
+    /// The socket's label, or else its name.
+    pub label: Option<&'static str>,
+    /// The name of the type that travels through the socket.
+    pub type_name: &'static str,
+    pub unit: Option<&'static str>,
+    pub range: Option<(f64, f64)>,
+    /// Whether the socket can be left unconnected (only for inputs).
+    pub optional: bool,
+}
+
+impl From<&SocketData> for SocketDescriptor {
+    fn from(socket: &SocketData) -> Self {
+        Self {
+            name: socket.name(),
+            label: socket.label(),
+            type_name: socket.type_name(),
+            unit: socket.unit(),
+            range: socket.range(),
+            optional: socket.is_optional(),
+        }
+    }
+}
+
+/// Turns `my_crate::nodes::Gain<f32>` into `Gain`.
+fn short_type_name(type_name: &'static str) -> &'static str {
+    let path = type_name.split('<').next().unwrap_or(type_name);
+    path.rsplit("::").next().unwrap_or(path)
+}
@@ -0,0 +1,131 @@
+//! # Graph Documents
+//!
+//! A [`GraphDocument`] is the plain-data form of a [`Graph`]: every node is saved as its
+//! registered type id and parameters, and every connection by the keys and socket indices
+//! it links. Documents implement `serde`'s traits, so they can be written in any format
+//! serde supports (TOML, JSON, RON...).
+//!
+//! Node keys are preserved, so a graph loaded back from a document can be addressed
+//! with the same keys it was saved with.
+
+use serde::{Deserialize, Serialize};
+use crate::graph::{ConnectError, ConnectionKind, Graph, NodeKey};
+use crate::registry::{NodeRegistry, Parameters, RegistryError};
+
+/// The serializable form of a [`Graph`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GraphDocument {
+    #[serde(default)]
+    pub nodes: Vec<NodeDocument>,
+    #[serde(default)]
+    pub edges: Vec<EdgeDocument>,
+}
+
+/// A single node of a [`GraphDocument`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NodeDocument {
+    /// The node's key in the graph.
+    pub id: usize,
+    /// The id the node's type was registered under.
+    #[serde(rename = "type")]
+    pub type_id: String,
+    #[serde(default)]
+    pub parameters: Parameters,
+    /// Where the node sits in an editor, if anywhere.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<[f32; 2]>,
+}
+
+/// A single connection of a [`GraphDocument`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EdgeDocument {
+    /// The output this edge starts at, as `(node id, socket index)`.
+    pub from: (usize, usize),
+    /// The input this edge ends at, as `(node id, socket index)`.
+    pub to: (usize, usize),
+    /// Whether this is a [`ConnectionKind::Feedback`] connection.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub feedback: bool,
+}
+
+/// An error that happened while converting between a [`Graph`] and a [`GraphDocument`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum DocumentError {
+    /// A node couldn't be saved or created by the registry.
+    Registry { node: NodeKey, error: RegistryError },
+    /// Two nodes of the document share the same id.
+    DuplicateNodeId(usize),
+    /// A node of the document has an id too large for a graph to hold.
+    NodeIdOutOfRange(usize),
+    /// An edge of the document couldn't be connected.
+    Connect(ConnectError),
+}
+
+impl GraphDocument {
+    /// Saves a graph, using `registry` to find each node's type and parameters.
+    ///
+    /// Fails if the graph contains a node whose type isn't registered.
+    pub fn from_graph(graph: &Graph, registry: &NodeRegistry) -> Result<Self, DocumentError> {
+        let nodes = graph
+            .nodes()
+            .map(|(key, node)| {
+                let (type_id, parameters) = registry
+                    .save(node)
+                    .map_err(|error| DocumentError::Registry { node: key, error })?;
+                Ok(NodeDocument {
+                    id: key.0,
+                    type_id: type_id.to_string(),
+                    parameters,
+                    position: graph.position(key),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut edges: Vec<EdgeDocument> = graph
+            .connections()
+            .map(|connection| EdgeDocument {
+                from: (connection.output_node.0, connection.output_socket),
+                to: (connection.input_node.0, connection.input_socket),
+                feedback: connection.kind == ConnectionKind::Feedback,
+            })
+            .collect();
+        // Connections come out of the graph in no particular order; keep saved files stable.
+        edges.sort_by_key(|edge| (edge.to, edge.from));
+
+        Ok(Self { nodes, edges })
+    }
+
+    /// Builds the graph this document describes, using `registry` to create its nodes.
+    pub fn to_graph(&self, registry: &NodeRegistry) -> Result<Graph, DocumentError> {
+        let mut graph = Graph::new();
+
+        for document in self.nodes.iter() {
+            if document.id == usize::MAX {
+                return Err(DocumentError::NodeIdOutOfRange(document.id));
+            }
+            let key = NodeKey(document.id);
+            let node = registry
+                .create(&document.type_id, &document.parameters)
+                .map_err(|error| DocumentError::Registry { node: key, error })?;
+            if !graph.insert_boxed_at(key, node) {
+                return Err(DocumentError::DuplicateNodeId(document.id));
+            }
+            if let Some(position) = document.position {
+                graph.set_position(key, position);
+            }
+        }
+
+        for edge in self.edges.iter() {
+            let (output_node, output_socket) = (NodeKey(edge.from.0), edge.from.1);
+            let (input_node, input_socket) = (NodeKey(edge.to.0), edge.to.1);
+            let result = if edge.feedback {
+                graph.connect_feedback(output_node, output_socket, input_node, input_socket)
+            } else {
+                graph.connect(output_node, output_socket, input_node, input_socket)
+            };
+            result.map_err(DocumentError::Connect)?;
+        }
+
+        Ok(graph)
+    }
+}
@@ -1,41 +1,395 @@
 use std::alloc::Layout;
 use std::any::TypeId;
-use std::collections::{HashMap, HashSet};
+use std::any::Any;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::ops::Range;
 use std::ptr::NonNull;
-use bumpalo::Bump;
+use std::sync::Arc;
+use crate::descriptor::NodeDescriptor;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 /// Function that represents a node's processing.
 ///
 /// When running, it will resolve and cast the pointers into the proper input and output types!
 pub type NodeFunc = fn(inputs: &[*const u8], outputs: &[*mut u8]);
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// Identifies a node inside a [`Graph`].
+///
+/// Keys are never reused, so a key stays valid (and keeps pointing at the same node)
+/// no matter how the graph is edited, until that node is removed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 #[repr(transparent)]
-pub struct NodeKey(usize);
+pub struct NodeKey(pub(crate) usize);
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 #[repr(transparent)]
 pub struct SocketIndex(usize);
 
+/// Points at one of a node's sockets, either by position or by name (see [`SocketData::named`]).
+///
+/// Functions that take an `impl Into<SocketRef>` accept both, so
+/// `graph.connect(osc, "out", gain, "signal")` and `graph.connect(osc, 0, gain, 0)` are the same.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SocketRef<'a> {
+    Index(usize),
+    Name(&'a str),
+}
+
+impl From<usize> for SocketRef<'_> {
+    fn from(index: usize) -> Self {
+        SocketRef::Index(index)
+    }
+}
+
+impl<'a> From<&'a str> for SocketRef<'a> {
+    fn from(name: &'a str) -> Self {
+        SocketRef::Name(name)
+    }
+}
+
 pub struct SocketData {
     layout: Layout,
+    type_id: TypeId,
+    type_name: &'static str,
+    /// Writes a valid value into the socket's storage before the pipeline first runs.
+    ///
+    /// Only input sockets may lack this, since they read from someone else's storage,
+    /// and outputs described with [`SocketData::uninit`].
+    init: Option<unsafe fn(NonNull<u8>)>,
+    /// Whether this output socket starts out uninitialized, for its node to write first.
+    uninit: bool,
+    /// Drops the value in the socket's storage, if the type has drop glue.
+    drop: Option<unsafe fn(NonNull<u8>)>,
+    /// Writes the value an input socket reads when nothing is connected to it.
+    default: Option<Box<dyn Fn(NonNull<u8>)>>,
+    /// What the socket is called, so it can be connected by name.
+    name: Option<&'static str>,
+    /// What the socket is called in a user interface.
+    label: Option<&'static str>,
+    /// The unit of the socket's value, like `"Hz"` or `"dB"`.
+    unit: Option<&'static str>,
+    /// The range a user interface should offer for the socket's value.
+    range: Option<(f64, f64)>,
+    /// Whether this input socket may be left unconnected, in which case it reads nothing.
+    optional: bool,
 }
 impl SocketData {
-    pub fn new<T: 'static>() -> Self {
+    /// Describes a socket carrying a `T`.
+    ///
+    /// Its storage starts out as `T::default()` and is dropped along with the pipeline,
+    /// so it's fine for sockets to carry heap-owning types like `Vec` or `String`.
+    pub fn new<T: Default + 'static>() -> Self {
+        unsafe fn init<T: Default>(pointer: NonNull<u8>) {
+            unsafe { pointer.cast::<T>().write(T::default()) }
+        }
+
+        Self {
+            layout: Layout::new::<T>(),
+            type_id: TypeId::of::<T>(),
+            type_name: std::any::type_name::<T>(),
+            init: Some(init::<T>),
+            uninit: false,
+            drop: Self::drop_glue::<T>(),
+            default: None,
+            name: None,
+            label: None,
+            unit: None,
+            range: None,
+            optional: false,
+        }
+    }
+
+    /// Describes an output socket carrying a `T` that has no sensible default,
+    /// like a reference or an iterator adapter.
+    ///
+    /// Its storage starts out uninitialized, so the node must write to it the first time
+    /// it processes, and it can't feed a feedback connection, which would read it before that.
+    /// Since that first write drops whatever was in the storage, `T` can't have drop glue.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `T` needs dropping.
+    pub fn uninit<T: 'static>() -> Self {
+        assert!(
+            !std::mem::needs_drop::<T>(),
+            "output socket of type `{}` has no default value but needs dropping",
+            std::any::type_name::<T>(),
+        );
+
+        Self {
+            layout: Layout::new::<T>(),
+            type_id: TypeId::of::<T>(),
+            type_name: std::any::type_name::<T>(),
+            init: None,
+            uninit: true,
+            drop: None,
+            default: None,
+            name: None,
+            label: None,
+            unit: None,
+            range: None,
+            optional: false,
+        }
+    }
+
+    /// Describes an input socket reading a `T`.
+    ///
+    /// Input sockets read from the storage of the output they're connected to,
+    /// so, unlike output sockets, they don't need to know how to create or drop a `T`.
+    pub fn input<T: 'static>() -> Self {
         Self {
             layout: Layout::new::<T>(),
+            type_id: TypeId::of::<T>(),
+            type_name: std::any::type_name::<T>(),
+            init: None,
+            uninit: false,
+            drop: None,
+            default: None,
+            name: None,
+            label: None,
+            unit: None,
+            range: None,
+            optional: false,
         }
     }
+
+    /// Lets this input socket be left unconnected, in which case it reads `value`.
+    ///
+    /// Input sockets without a default must be connected for the graph to compile.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `T` isn't the type this socket carries.
+    pub fn with_default<T: Clone + 'static>(mut self, value: T) -> Self {
+        assert_eq!(
+            self.type_id,
+            TypeId::of::<T>(),
+            "default value of type `{}` given to a socket of type `{}`",
+            std::any::type_name::<T>(),
+            self.type_name,
+        );
+        self.drop = Self::drop_glue::<T>();
+        self.default = Some(Box::new(move |pointer| unsafe {
+            pointer.cast::<T>().write(value.clone())
+        }));
+        self
+    }
+
+    /// Lets this input socket be left unconnected, in which case the node reads nothing from it.
+    ///
+    /// Nodes read optional inputs with [`crate::as_optional_input`].
+    pub fn optional(mut self) -> Self {
+        self.optional = true;
+        self
+    }
+
+    /// Names this socket, so it can be connected by name (see [`SocketRef`]).
+    ///
+    /// Names should be unique among a node's input sockets, and among its output sockets.
+    pub fn named(mut self, name: &'static str) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// Sets what the socket is called in a user interface.
+    pub fn with_label(mut self, label: &'static str) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    /// Sets the unit of the socket's value, like `"Hz"` or `"dB"`.
+    pub fn with_unit(mut self, unit: &'static str) -> Self {
+        self.unit = Some(unit);
+        self
+    }
+
+    /// Sets the range a user interface should offer for the socket's value.
+    ///
+    /// This is only a hint, nothing clamps the values that travel through the socket.
+    pub fn with_range(mut self, min: f64, max: f64) -> Self {
+        self.range = Some((min, max));
+        self
+    }
+
+    fn drop_glue<T>() -> Option<unsafe fn(NonNull<u8>)> {
+        unsafe fn drop<T>(pointer: NonNull<u8>) {
+            unsafe { pointer.cast::<T>().drop_in_place() }
+        }
+
+        std::mem::needs_drop::<T>().then_some(drop::<T> as unsafe fn(NonNull<u8>))
+    }
+
+    /// The [`TypeId`] of the value that travels through this socket.
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    /// The name of the type that travels through this socket, for diagnostics.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// The socket's name, if it has one.
+    pub fn name(&self) -> Option<&'static str> {
+        self.name
+    }
+
+    /// What the socket should be called in a user interface: its label, or else its name.
+    pub fn label(&self) -> Option<&'static str> {
+        self.label.or(self.name)
+    }
+
+    /// The unit of the socket's value, if it has one.
+    pub fn unit(&self) -> Option<&'static str> {
+        self.unit
+    }
+
+    /// The range a user interface should offer for the socket's value, as `(min, max)`.
+    pub fn range(&self) -> Option<(f64, f64)> {
+        self.range
+    }
+
+    /// Whether this input socket has a default value (see [`SocketData::with_default`]).
+    pub fn has_default(&self) -> bool {
+        self.default.is_some()
+    }
+
+    /// Whether this input socket can be left unconnected, either because it's
+    /// [optional](SocketData::optional) or because it has a default.
+    pub fn is_optional(&self) -> bool {
+        self.optional || self.default.is_some()
+    }
+
+    /// Whether a value coming out of `self` can be read by a socket described by `other`.
+    pub fn is_compatible_with(&self, other: &SocketData) -> bool {
+        self.type_id == other.type_id
+    }
 }
 
-pub trait Node {
+/// Lets `#[node_impl]` pick between [`SocketData::new`] and [`SocketData::uninit`]
+/// without knowing which traits a socket's type implements.
+///
+/// `(&SocketProbe::<T>::new()).socket_data()` resolves to [`ViaDefault`] when `T: Default`
+/// and falls back to [`ViaUninit`] (through auto-ref) otherwise.
+#[doc(hidden)]
+pub mod probe {
+    use super::SocketData;
+    use std::marker::PhantomData;
+
+    pub struct SocketProbe<T>(PhantomData<T>);
+
+    impl<T> SocketProbe<T> {
+        pub fn new() -> Self {
+            Self(PhantomData)
+        }
+    }
+
+    impl<T> Default for SocketProbe<T> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    pub trait ViaDefault {
+        fn socket_data(&self) -> SocketData;
+    }
+
+    impl<T: Default + 'static> ViaDefault for SocketProbe<T> {
+        fn socket_data(&self) -> SocketData {
+            SocketData::new::<T>()
+        }
+    }
+
+    pub trait ViaUninit {
+        fn socket_data(&self) -> SocketData;
+    }
+
+    impl<T: 'static> ViaUninit for &SocketProbe<T> {
+        fn socket_data(&self) -> SocketData {
+            const {
+                assert!(
+                    !std::mem::needs_drop::<T>(),
+                    "an output socket whose type isn't `Default` can't carry a type that needs dropping"
+                )
+            };
+            SocketData::uninit::<T>()
+        }
+    }
+}
+
+/// Information about the run of a [`GraphPipeline`] that's in progress,
+/// handed to every node each time it processes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ProcessContext {
+    /// How many frames make up a second of audio.
+    pub sample_rate: u32,
+    /// How many frames each node should process during this run.
+    ///
+    /// Block-based sockets (like [`crate::audio::AudioBlock`]) have a fixed capacity,
+    /// this is how much of it is in use.
+    pub block_len: usize,
+    /// The index of the first frame of this run, counted from the start of the transport.
+    pub frame: u64,
+    /// Whether the transport is playing or stopped.
+    pub playing: bool,
+    /// Whether nodes should throw away their running state (phases, filter history,
+    /// random generators...) before processing this block, as if they were just bound.
+    ///
+    /// The pipeline sets this on the first run after [`GraphPipeline::reset`],
+    /// nodes written with `#[node_impl]` handle it by themselves.
+    pub reset: bool,
+}
+
+impl ProcessContext {
+    pub fn new(sample_rate: u32, block_len: usize) -> Self {
+        Self {
+            sample_rate,
+            block_len,
+            frame: 0,
+            playing: true,
+            reset: false,
+        }
+    }
+
+    /// The position of the first frame of this run, in seconds.
+    ///
+    /// Without a sample rate, time doesn't pass, so it's always 0.
+    pub fn time(&self) -> f64 {
+        if self.sample_rate == 0 {
+            return 0.0;
+        }
+        self.frame as f64 / self.sample_rate as f64
+    }
+
+    /// Moves the context to the next block, if the transport is playing.
+    pub fn advance(&mut self) {
+        if self.playing {
+            self.frame += self.block_len as u64;
+        }
+    }
+}
+
+impl Default for ProcessContext {
+    /// One frame per run at 44.1kHz, starting at the beginning.
+    fn default() -> Self {
+        Self::new(44100, 1)
+    }
+}
+
+pub trait Node: Any {
     /// Returns a function that processes the node in terms of its parameters.
     ///
     /// It takes self here simply to be dyn-compatible.
     ///
     /// TODO: Maybe instead of returning a boxed closure, which is going to be
     /// put in a Vec anyways, maybe pass an arena for `bind_parameters` to allocate the closure in.
-    fn bind_parameters<'pip>(&self, parameters: &mut dyn Iterator<Item = NonNull<u8>>) -> Box<dyn FnMut() + 'pip>;
+    ///
+    /// The function is called once per run of the pipeline, with information about that run.
+    /// It must be `Send`, since pipelines can run nodes from worker threads.
+    ///
+    /// Running state (like an oscillator's phase) belongs in the function, not in the node,
+    /// and should go back to its initial value whenever [`ProcessContext::reset`] is set.
+    fn bind_parameters<'pip>(&self, parameters: &mut dyn Iterator<Item = NonNull<u8>>) -> Box<dyn FnMut(&ProcessContext) + Send + 'pip>;
 
     /// Returns data about an input socket.
     /// Take self so the trait is dyn-compatible.
@@ -44,23 +398,85 @@ pub trait Node {
     /// Returns data about an output socket.
     /// Take self so the trait is dyn-compatible.
     fn output_socket(&self, socket_index: usize) -> Option<SocketData>;
+
+    /// Describes the node to a user interface.
+    ///
+    /// By default, the node is named after its type and filed as uncategorized.
+    fn descriptor(&self) -> NodeDescriptor {
+        NodeDescriptor::of(self)
+    }
+    /// Puts the running state the node keeps in itself back to its initial value,
+    /// like when playback jumps to another point in time. [`Graph::reset`] calls this on every node.
+    ///
+    /// Does nothing by default, since running state usually lives in the bound function,
+    /// which resets when [`ProcessContext::reset`] is set (as it is after [`Graph::reset`] too).
+    fn reset(&mut self) {}
 }
 
 pub struct Graph {
-    nodes: HashMap<NodeKey, Box<dyn Node>>,
+    nodes: BTreeMap<NodeKey, Box<dyn Node>>,
     // The key is some node's input socket, the value is some node's output socket
     // Data flows in this direction   <----
     // But it's a pull model so we hash the input.
-    edges: HashMap<(NodeKey, SocketIndex), (NodeKey, SocketIndex)>,
+    edges: HashMap<(NodeKey, SocketIndex), Edge>,
     next_id: usize,
+    /// Where each node sits in a graph editor, if anywhere.
+    positions: HashMap<NodeKey, [f32; 2]>,
+    /// How many times each node was borrowed mutably, so pipelines know when to bind it again.
+    revisions: HashMap<NodeKey, u64>,
+    /// How many times the graph was reset, shared with the pipelines compiled from it
+    /// so they reset on their next run.
+    resets: Arc<AtomicU64>,
+}
+
+/// How the value of an output socket reaches an input socket.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ConnectionKind {
+    /// The input reads what the output wrote during the same run,
+    /// so the output's node always runs first.
+    Direct,
+    /// The input reads what the output wrote during the _previous_ run.
+    ///
+    /// This one-run delay is what allows a graph to loop back on itself
+    /// (echoes, comb filters, reverb networks...). On the very first run
+    /// the input reads the socket's initial value.
+    Feedback,
+}
+
+/// Which of a node's socket lists to look in.
+#[derive(Clone, Copy)]
+enum SocketSide {
+    Input,
+    Output,
+}
+
+/// A connection between an output socket and an input socket, as seen from outside the graph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Connection {
+    pub output_node: NodeKey,
+    pub output_socket: usize,
+    pub input_node: NodeKey,
+    pub input_socket: usize,
+    pub kind: ConnectionKind,
+}
+
+/// An edge, as seen from the input socket it points to.
+#[derive(Clone, Copy, Debug)]
+struct Edge {
+    node: NodeKey,
+    socket: SocketIndex,
+    kind: ConnectionKind,
 }
 
 impl Graph {
     pub fn new() -> Self {
         Self {
-            nodes: HashMap::new(),
+            nodes: BTreeMap::new(),
             edges: HashMap::new(),
             next_id: 0,
+            positions: HashMap::new(),
+            revisions: HashMap::new(),
+            resets: Arc::new(AtomicU64::new(0)),
         }
     }
 
@@ -68,142 +484,637 @@ impl Graph {
     ///
     /// The function returns a key which can be used to access the node temporarily.
     pub fn insert<N: Node + 'static>(&mut self, node: N) -> NodeKey {
+        self.insert_boxed(Box::new(node))
+    }
+
+    /// Like [`Graph::insert`], for nodes whose type is only known at runtime
+    /// (like the ones created by a [`crate::registry::NodeRegistry`]).
+    pub fn insert_boxed(&mut self, node: Box<dyn Node>) -> NodeKey {
         let index = self.next_id;
-        self.next_id += 1;
-        self.nodes.insert(NodeKey(index), Box::new(node));
+        self.next_id = index.checked_add(1).expect("ran out of node keys");
+        self.nodes.insert(NodeKey(index), node);
         NodeKey(index)
     }
 
+    /// Inserts a node under a specific key, for restoring saved graphs.
+    ///
+    /// Returns `false` (and doesn't insert anything) if the key is taken,
+    /// or is the largest possible key, which would leave none for nodes inserted later.
+    pub(crate) fn insert_boxed_at(&mut self, key: NodeKey, node: Box<dyn Node>) -> bool {
+        let Some(next_id) = key.0.checked_add(1) else {
+            return false;
+        };
+        if self.nodes.contains_key(&key) {
+            return false;
+        }
+        self.next_id = self.next_id.max(next_id);
+        self.nodes.insert(key, node);
+        true
+    }
+
+    /// Removes a node from the graph, along with every connection to or from it.
+    ///
+    /// Returns the node, if it was in the graph.
+    pub fn remove(&mut self, node: NodeKey) -> Option<Box<dyn Node>> {
+        let removed = self.nodes.remove(&node)?;
+        self.positions.remove(&node);
+        self.revisions.remove(&node);
+        self.edges
+            .retain(|(to_node, _), edge| *to_node != node && edge.node != node);
+        Some(removed)
+    }
+
+    /// Returns whether there's a node with this key in the graph.
+    pub fn contains(&self, node: NodeKey) -> bool {
+        self.nodes.contains_key(&node)
+    }
+
+    /// Iterates over every node in the graph, in insertion order.
+    pub fn nodes(&self) -> impl Iterator<Item = (NodeKey, &dyn Node)> {
+        self.nodes.iter().map(|(key, node)| (*key, node.as_ref()))
+    }
+
+    /// Returns a node as a trait object, if it exists.
+    pub fn node(&self, node: NodeKey) -> Option<&dyn Node> {
+        self.nodes.get(&node).map(|node| node.as_ref())
+    }
+
+    /// Returns where a node sits in a graph editor, if it was ever placed.
+    pub fn position(&self, node: NodeKey) -> Option<[f32; 2]> {
+        self.positions.get(&node).copied()
+    }
+
+    /// Places a node somewhere in a graph editor.
+    ///
+    /// The position means nothing to the graph itself, it's only kept so it can be saved.
+    pub fn set_position(&mut self, node: NodeKey, position: [f32; 2]) {
+        if self.nodes.contains_key(&node) {
+            self.positions.insert(node, position);
+        }
+    }
+
+    /// Returns a reference to a node, if it exists and is of type `N`.
+    pub fn get<N: Node>(&self, node: NodeKey) -> Option<&N> {
+        let node: &dyn Any = self.nodes.get(&node)?.as_ref();
+        node.downcast_ref()
+    }
+
+    /// Returns a mutable reference to a node, if it exists and is of type `N`.
+    ///
+    /// Changes to the node only reach pipelines compiled (or patched) after the change.
+    /// Patching a pipeline binds the node again, which resets its state.
+    pub fn get_mut<N: Node>(&mut self, node: NodeKey) -> Option<&mut N> {
+        let key = node;
+        let node: &mut dyn Any = self.nodes.get_mut(&key)?.as_mut();
+        let node = node.downcast_mut()?;
+        *self.revisions.entry(key).or_default() += 1;
+        Some(node)
+    }
+
+    /// Resets every node (see [`Node::reset`]), and the pipelines compiled from this graph
+    /// on their next run, as with [`GraphPipeline::reset`].
+    ///
+    /// This doesn't count as editing the nodes, so the pipelines keep their bound functions,
+    /// and the state in them is reset through [`ProcessContext::reset`].
+    pub fn reset(&mut self) {
+        for node in self.nodes.values_mut() {
+            node.reset();
+        }
+        self.resets.fetch_add(1, Ordering::Release);
+    }
+
+    /// How many times a node was edited through [`Graph::get_mut`].
+    fn revision(&self, node: NodeKey) -> u64 {
+        self.revisions.get(&node).copied().unwrap_or(0)
+    }
+
     /// Draws a connection from a node's output socket to another node's input socket.
     ///
     /// # Usage
     ///
     /// Please realize that data flows from the output of a node to the input of the other node.
-    pub fn connect(
+    ///
+    /// The connection is refused if either socket doesn't exist, if the sockets carry
+    /// different types, or if the input socket is already connected to something else.
+    pub fn connect<'a>(
+        &mut self,
+        output_node: NodeKey,
+        output_socket: impl Into<SocketRef<'a>>,
+        input_node: NodeKey,
+        input_socket: impl Into<SocketRef<'a>>,
+    ) -> Result<(), ConnectError> {
+        self.connect_with_kind(
+            output_node,
+            output_socket.into(),
+            input_node,
+            input_socket.into(),
+            ConnectionKind::Direct,
+        )
+    }
+
+    /// Like [`Graph::connect`], but the input will read the value the output
+    /// produced during the previous run of the pipeline.
+    ///
+    /// Use this to close loops in the graph — a loop made only of direct connections
+    /// can't be compiled.
+    pub fn connect_feedback<'a>(
+        &mut self,
+        output_node: NodeKey,
+        output_socket: impl Into<SocketRef<'a>>,
+        input_node: NodeKey,
+        input_socket: impl Into<SocketRef<'a>>,
+    ) -> Result<(), ConnectError> {
+        self.connect_with_kind(
+            output_node,
+            output_socket.into(),
+            input_node,
+            input_socket.into(),
+            ConnectionKind::Feedback,
+        )
+    }
+
+    fn connect_with_kind(
         &mut self,
         output_node: NodeKey,
-        output_socket: usize,
+        output_socket: SocketRef,
         input_node: NodeKey,
-        input_socket: usize,
-    ) -> Result<(), ()> {
+        input_socket: SocketRef,
+        kind: ConnectionKind,
+    ) -> Result<(), ConnectError> {
+        let (output_socket, output_data) = self.find_socket(output_node, output_socket, SocketSide::Output)?;
+        let (input_socket, input_data) = self.find_socket(input_node, input_socket, SocketSide::Input)?;
+
+        if !output_data.is_compatible_with(&input_data) {
+            return Err(ConnectError::TypeMismatch {
+                output_type: output_data.type_name(),
+                input_type: input_data.type_name(),
+            });
+        }
+
+        if let Some(edge) = self.edges.get(&(input_node, SocketIndex(input_socket))) {
+            return Err(ConnectError::InputAlreadyConnected {
+                connected_node: edge.node,
+                connected_socket: edge.socket.0,
+            });
+        }
+
         self.edges.insert(
             (input_node, SocketIndex(input_socket)),
-            (output_node, SocketIndex(output_socket)),
+            Edge {
+                node: output_node,
+                socket: SocketIndex(output_socket),
+                kind,
+            },
         );
         Ok(())
     }
 
+    /// Looks up one of a node's sockets, returning its index and description.
+    fn find_socket(
+        &self,
+        node: NodeKey,
+        socket: SocketRef,
+        side: SocketSide,
+    ) -> Result<(usize, SocketData), ConnectError> {
+        let node_ref = self.nodes.get(&node).ok_or(ConnectError::NoSuchNode(node))?;
+        let describe = |index| match side {
+            SocketSide::Input => node_ref.input_socket(index),
+            SocketSide::Output => node_ref.output_socket(index),
+        };
+
+        match socket {
+            SocketRef::Index(index) => describe(index).map(|data| (index, data)).ok_or(match side {
+                SocketSide::Input => ConnectError::NoSuchInputSocket { node, socket: index },
+                SocketSide::Output => ConnectError::NoSuchOutputSocket { node, socket: index },
+            }),
+            SocketRef::Name(name) => (0..)
+                .map_while(|index| describe(index).map(|data| (index, data)))
+                .find(|(_, data)| data.name == Some(name))
+                .ok_or_else(|| ConnectError::NoSuchSocketName {
+                    node,
+                    name: name.to_string(),
+                }),
+        }
+    }
+
+    /// Removes whatever is connected to a node's input socket.
+    ///
+    /// Returns the removed connection, if there was one.
+    pub fn disconnect<'a>(&mut self, input_node: NodeKey, input_socket: impl Into<SocketRef<'a>>) -> Option<Connection> {
+        let (input_socket, _) = self
+            .find_socket(input_node, input_socket.into(), SocketSide::Input)
+            .ok()?;
+        let edge = self.edges.remove(&(input_node, SocketIndex(input_socket)))?;
+        Some(Connection {
+            output_node: edge.node,
+            output_socket: edge.socket.0,
+            input_node,
+            input_socket,
+            kind: edge.kind,
+        })
+    }
+
+    /// Returns every connection that leaves one of this node's output sockets,
+    /// sorted by output socket.
+    pub fn edges_from(&self, node: NodeKey) -> Vec<Connection> {
+        let mut connections: Vec<Connection> = self
+            .connections()
+            .filter(|connection| connection.output_node == node)
+            .collect();
+        connections.sort_by_key(|c| (c.output_socket, c.input_node, c.input_socket));
+        connections
+    }
+
+    /// Returns every connection that arrives at one of this node's input sockets,
+    /// sorted by input socket.
+    pub fn edges_into(&self, node: NodeKey) -> Vec<Connection> {
+        let mut connections: Vec<Connection> = self
+            .connections()
+            .filter(|connection| connection.input_node == node)
+            .collect();
+        connections.sort_by_key(|c| c.input_socket);
+        connections
+    }
+
+    /// Iterates over every connection in the graph, in no particular order.
+    pub fn connections(&self) -> impl Iterator<Item = Connection> + '_ {
+        self.edges
+            .iter()
+            .map(|((input_node, input_socket), edge)| Connection {
+                output_node: edge.node,
+                output_socket: edge.socket.0,
+                input_node: *input_node,
+                input_socket: input_socket.0,
+                kind: edge.kind,
+            })
+    }
+
     /// Compiles this graph (from the perspective of a sink)
     /// so it can be executed thousands a time a second.
-    pub fn compile(&self, sink: NodeKey, sink_socket: usize) -> GraphPipeline {
-        GraphPipeline::from_graph(self, sink, sink_socket)
+    ///
+    /// `sink_socket` is the output socket the pipeline is for, though all of the sink's
+    /// outputs are computed. Prefer [`Graph::compile_sinks`], which also works for sinks without outputs.
+    ///
+    /// Fails if the sink has no such output socket, or if the nodes the sink depends on
+    /// are connected in a loop that isn't broken by a [`ConnectionKind::Feedback`] connection.
+    pub fn compile(&self, sink: NodeKey, sink_socket: usize) -> Result<GraphPipeline, CompileError> {
+        let node = self.nodes.get(&sink).ok_or(CompileError::NoSuchNode(sink))?;
+        if node.output_socket(sink_socket).is_none() {
+            return Err(CompileError::NoSuchSocket {
+                node: sink,
+                socket: sink_socket,
+            });
+        }
+        self.compile_sinks(&[sink])
+    }
+
+    /// Compiles a single pipeline that drives all the given sinks.
+    ///
+    /// Nodes that several sinks depend on run only once per run of the pipeline,
+    /// so a preview, a meter and an exporter can all share the same mix.
+    pub fn compile_sinks(&self, sinks: &[NodeKey]) -> Result<GraphPipeline, CompileError> {
+        GraphPipeline::from_graph(self, sinks)
+    }
+
+    /// Compiles a single pipeline that drives every sink of the graph (see [`Graph::sinks`]).
+    ///
+    /// Patching the pipeline (see [`GraphPipeline::recompile`]) picks up sinks added since.
+    pub fn compile_all(&self) -> Result<GraphPipeline, CompileError> {
+        GraphPipeline::from_selection(self, None)
+    }
+
+    /// Returns the terminal nodes of the graph, that is, nodes none of whose outputs
+    /// are connected to anything (like exporters, meters or previews).
+    pub fn sinks(&self) -> Vec<NodeKey> {
+        let feeding: HashSet<NodeKey> = self.edges.values().map(|edge| edge.node).collect();
+        self.nodes
+            .keys()
+            .filter(|node| !feeding.contains(node))
+            .copied()
+            .collect()
+    }
+
+    /// Returns every node that the `sinks` depend on (including themselves),
+    /// following both direct and feedback connections.
+    fn upstream_of(&self, sinks: &[NodeKey]) -> HashSet<NodeKey> {
+        let mut reached = HashSet::new();
+        let mut stack = sinks.to_vec();
+
+        while let Some(node) = stack.pop() {
+            if !reached.insert(node) {
+                continue;
+            }
+            stack.extend(
+                self.edges
+                    .iter()
+                    .filter(|((to_node, _), _)| *to_node == node)
+                    .map(|(_, edge)| edge.node),
+            );
+        }
+
+        reached
+    }
+
+    /// Sorts `nodes` so that every node comes after the nodes it directly depends on.
+    ///
+    /// If that's impossible, returns the nodes that are stuck in (or between) loops.
+    fn topological_order(&self, nodes: &HashSet<NodeKey>) -> Result<Vec<NodeKey>, Vec<NodeKey>> {
+        let direct_edges = || {
+            self.edges
+                .iter()
+                .filter(|(_, edge)| edge.kind == ConnectionKind::Direct)
+                .filter(|((to_node, _), _)| nodes.contains(to_node))
+                .map(|((to_node, _), edge)| (edge.node, *to_node))
+        };
+
+        let mut dependency_count: HashMap<NodeKey, usize> =
+            nodes.iter().map(|node| (*node, 0)).collect();
+        for (_, to_node) in direct_edges() {
+            *dependency_count.get_mut(&to_node).unwrap() += 1;
+        }
+
+        // Sorted so that compiling the same graph always yields the same order.
+        let mut ready: Vec<NodeKey> = dependency_count
+            .iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(node, _)| *node)
+            .collect();
+        ready.sort();
+        let mut ready = VecDeque::from(ready);
+
+        let mut order = Vec::with_capacity(nodes.len());
+        while let Some(node) = ready.pop_front() {
+            order.push(node);
+
+            let mut unlocked = Vec::new();
+            for (from_node, to_node) in direct_edges() {
+                if from_node != node {
+                    continue;
+                }
+                let count = dependency_count.get_mut(&to_node).unwrap();
+                *count -= 1;
+                if *count == 0 {
+                    unlocked.push(to_node);
+                }
+            }
+            unlocked.sort();
+            ready.extend(unlocked);
+        }
+
+        if order.len() == nodes.len() {
+            return Ok(order);
+        }
+
+        // Whatever wasn't ordered is either in a loop or downstream of one.
+        // Peel off the downstream nodes so that only the offending ones are reported.
+        let mut stuck: HashSet<NodeKey> = dependency_count
+            .into_iter()
+            .filter(|(_, count)| *count > 0)
+            .map(|(node, _)| node)
+            .collect();
+        loop {
+            let dead_ends: Vec<NodeKey> = stuck
+                .iter()
+                .filter(|node| {
+                    !direct_edges().any(|(from_node, to_node)| {
+                        from_node == **node && stuck.contains(&to_node)
+                    })
+                })
+                .copied()
+                .collect();
+            if dead_ends.is_empty() {
+                break;
+            }
+            for node in dead_ends {
+                stuck.remove(&node);
+            }
+        }
+
+        let mut stuck: Vec<NodeKey> = stuck.into_iter().collect();
+        stuck.sort();
+        Err(stuck)
     }
 }
 
-pub struct GraphPipeline {
-    /// Contains one allocation per graph edge.
-    /// Shared memory space that the nodes use to do work.
-    arena: Bump,
+/// An error that happened while connecting two sockets with [`Graph::connect`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectError {
+    /// There is no node with this key in the graph.
+    NoSuchNode(NodeKey),
+    /// The node has no output socket at this index.
+    NoSuchOutputSocket { node: NodeKey, socket: usize },
+    /// The node has no input socket at this index.
+    NoSuchInputSocket { node: NodeKey, socket: usize },
+    /// The node has no socket with this name, on the side it was looked for.
+    NoSuchSocketName { node: NodeKey, name: String },
+    /// The output socket produces a type that the input socket doesn't accept.
+    TypeMismatch {
+        output_type: &'static str,
+        input_type: &'static str,
+    },
+    /// The input socket is already being fed by another output socket.
+    ///
+    /// An input socket reads from exactly one place, so disconnect it first.
+    InputAlreadyConnected {
+        connected_node: NodeKey,
+        connected_socket: usize,
+    },
+}
+
+/// An error that happened while compiling a [`Graph`] into a [`GraphPipeline`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompileError {
+    /// There is no node with this key in the graph.
+    NoSuchNode(NodeKey),
+    /// The node has no output socket with this index.
+    NoSuchSocket { node: NodeKey, socket: usize },
+    /// These nodes are connected in a loop made only of direct connections,
+    /// so there's no order in which they could run.
+    ///
+    /// Break the loop with [`Graph::connect_feedback`].
+    Cycle(Vec<NodeKey>),
+    /// This input socket isn't connected to anything, has no default value
+    /// (see [`SocketData::with_default`]) and isn't optional (see [`SocketData::optional`]).
+    UnconnectedInput { node: NodeKey, socket: usize },
+    /// This output feeds a feedback connection, but starts out uninitialized
+    /// (see [`SocketData::uninit`]), so there's nothing for the connection to read on the first run.
+    UninitFeedback { node: NodeKey, socket: usize },
+}
 
+pub struct GraphPipeline {
     /// Contains a list of functions, one for each node of the graph
     /// in topological order, i.e., dependency order.
     ///
-    /// So it's safe from a function to read from anywhere in `edge_data`,
-    /// since it will always be ordered _after_ a function that set a value there.
-    vertices: Vec<Box<dyn FnMut()>>,
+    /// So it's safe from a function to read from any of its inputs,
+    /// since it will always be ordered _after_ the function that set a value there.
+    vertices: Vec<Vertex>,
+
+    /// The storage of each vertex's output sockets, in the same order.
+    ///
+    /// Every node owns its own allocation, so patching the pipeline can keep the
+    /// storage (and the values in it) of the nodes it doesn't touch.
+    storage: Vec<SocketBlock>,
+
+    /// The storage of each vertex's default inputs, in the same order.
+    ///
+    /// Defaults can depend on a node's fields, so they're made again when
+    /// the node is edited, even if its outputs are kept.
+    default_storage: Vec<SocketBlock>,
+
+    /// The storage of each delayed copy in `layout.feedback`, in the same order.
+    feedback_storage: Vec<SocketBlock>,
+
+    /// Ranges of `vertices` whose nodes don't depend on each other, in order.
+    levels: Vec<Range<usize>>,
+
+    /// Whether the next run should tell nodes to reset their state.
+    reset_pending: bool,
+
+    /// How many resets of the graph this pipeline has gone through (see [`Graph::reset`]).
+    seen_resets: u64,
+
+    /// What was bound where, shared with any [`PipelineEditor`]s.
+    layout: Arc<PipelineLayout>,
+
+    /// Worker threads for running the nodes of a level in parallel, if enabled.
+    #[cfg(feature = "parallel")]
+    pool: Option<rayon::ThreadPool>,
 }
 
+// SAFETY: The pointers only point into the pipeline's own socket storage, and every vertex is `Send`.
+unsafe impl Send for GraphPipeline {}
+
 impl GraphPipeline {
-    pub fn from_graph(graph: &Graph, sink_node: NodeKey, sink_socket: usize) -> Self {
-        //let mut edge_data = Vec::with_capacity(256);
-        let mut arena = Bump::new();
-        let mut vertices = Vec::with_capacity(graph.nodes.len());
-        let mut visited_nodes = HashSet::<NodeKey>::new();
-        let mut socket_pointers = HashMap::<(NodeKey, SocketIndex), NonNull<u8>>::new();
-
-        fn depth_first_traverse(
-            current_node_key: NodeKey,
-            current_output_socket: SocketIndex,
-            graph: &Graph,
-            visited_nodes: &mut HashSet<NodeKey>,
-            arena: &mut Bump,
-            output_pointer_cache: &mut HashMap<(NodeKey, SocketIndex), NonNull<u8>>,
-            vertices: &mut Vec<Box<dyn FnMut()>>,
-        ) -> Option<NonNull<u8>> {
-            if visited_nodes.contains(&current_node_key) {
-                return Some(
-                    output_pointer_cache
-                        .get(&(current_node_key, current_output_socket))
-                        .copied()
-                        .unwrap(),
-                );
-            }
-            visited_nodes.insert(current_node_key);
+    /// Compiles a pipeline that drives the given sinks.
+    /// See [`Graph::compile_sinks`].
+    pub fn from_graph(graph: &Graph, sinks: &[NodeKey]) -> Result<Self, CompileError> {
+        Self::from_selection(graph, Some(sinks.to_vec()))
+    }
 
-            /// TODO: Sort the edges based on the current node's socket order.
-            let input_pointers = graph
-                .edges
-                .iter()
-                .filter(|((to_node, _), _)| *to_node == current_node_key)
-                .map(|(to, from)| {
-                    let input_pointer = depth_first_traverse(
-                        from.0,
-                        from.1,
-                        graph,
-                        visited_nodes,
-                        arena,
-                        output_pointer_cache,
-                        vertices,
-                    )
-                    .unwrap();
-
-                    input_pointer
-                })
-                .collect::<Vec<_>>();
+    /// Compiles a pipeline for `sinks`, or for every sink of the graph if `None`.
+    fn from_selection(graph: &Graph, sinks: Option<Vec<NodeKey>>) -> Result<Self, CompileError> {
+        let layout = Arc::new(PipelineLayout {
+            version: next_layout_version(),
+            sinks,
+            nodes: Vec::new(),
+            feedback: Vec::new(),
+            resets: graph.resets.clone(),
+        });
+        let editor = PipelineEditor { layout: layout.clone() };
+        let mut pipeline = Self {
+            vertices: Vec::new(),
+            storage: Vec::new(),
+            default_storage: Vec::new(),
+            feedback_storage: Vec::new(),
+            levels: Vec::new(),
+            reset_pending: false,
+            seen_resets: graph.resets.load(Ordering::Acquire),
+            layout,
+            #[cfg(feature = "parallel")]
+            pool: None,
+        };
 
-            let current_node = graph.nodes.get(&current_node_key).unwrap();
+        let patch = editor.patch(graph)?;
+        if pipeline.apply(patch).is_err() {
+            unreachable!("the patch was made from this very pipeline");
+        }
+        Ok(pipeline)
+    }
 
-            let mut output_pointer_to_return = None;
+    /// Returns an editor that prepares patches for this pipeline.
+    ///
+    /// The editor can be sent to another thread, so the audio thread only has to
+    /// [apply](GraphPipeline::apply) the finished patch.
+    pub fn editor(&self) -> PipelineEditor {
+        PipelineEditor {
+            layout: self.layout.clone(),
+        }
+    }
 
-            let output_pointers = (0..)
-                .map_while(|output_index| {
-                    current_node
-                        .output_socket(output_index)
-                        .map(|output| (output_index, output))
-                })
-                .map(|(output_index, output)| {
-                    let output_pointer = arena.alloc_layout(output.layout);
-                    output_pointer_cache
-                        .insert((current_node_key, SocketIndex(output_index)), output_pointer);
+    /// Swaps in a patch made by one of this pipeline's editors.
+    ///
+    /// This doesn't allocate nor bind anything, it only moves boxes around, so it's
+    /// fine to call from the audio thread between two runs. The parts of the pipeline
+    /// that were replaced are returned, so they can be dropped somewhere else.
+    ///
+    /// The patch is given back if it wasn't made for the pipeline as it is now —
+    /// for example, if another patch was applied since.
+    // Boxing the patch in the error would allocate, and this runs on the audio thread.
+    #[allow(clippy::result_large_err)]
+    pub fn apply(&mut self, mut patch: PipelinePatch) -> Result<RetiredPipeline, PipelinePatch> {
+        if patch.base != self.layout.version {
+            return Err(patch);
+        }
 
-                    if output_index == current_output_socket.0 {
-                        output_pointer_to_return = Some(output_pointer);
-                    }
+        if !Arc::ptr_eq(&self.layout.resets, &patch.layout.resets) {
+            // Patched from another graph, whose resets this pipeline didn't go through.
+            self.seen_resets = patch.layout.resets.load(Ordering::Acquire);
+        }
+        for &(new, old) in patch.kept_vertices.iter() {
+            std::mem::swap(&mut patch.vertices[new], &mut self.vertices[old]);
+        }
+        for &(new, old) in patch.kept_storage.iter() {
+            std::mem::swap(&mut patch.storage[new], &mut self.storage[old]);
+        }
+        for &(new, old) in patch.kept_default_storage.iter() {
+            std::mem::swap(&mut patch.default_storage[new], &mut self.default_storage[old]);
+        }
+        for &(new, old) in patch.kept_feedback.iter() {
+            std::mem::swap(&mut patch.feedback_storage[new], &mut self.feedback_storage[old]);
+        }
 
-                    output_pointer
-                });
+        std::mem::swap(&mut self.vertices, &mut patch.vertices);
+        std::mem::swap(&mut self.storage, &mut patch.storage);
+        std::mem::swap(&mut self.default_storage, &mut patch.default_storage);
+        std::mem::swap(&mut self.feedback_storage, &mut patch.feedback_storage);
+        std::mem::swap(&mut self.levels, &mut patch.levels);
+        std::mem::swap(&mut self.layout, &mut patch.layout);
 
-            let mut parameter_iterator = input_pointers.into_iter().chain(output_pointers);
-            let vertex = current_node.bind_parameters(&mut parameter_iterator);
-            vertices.push(vertex);
-            
-            output_pointer_to_return
-        }
-
-        let sink_output_pointer = depth_first_traverse(
-            sink_node,
-            SocketIndex(sink_socket),
-            graph,
-            &mut visited_nodes,
-            &mut arena,
-            &mut socket_pointers,
-            &mut vertices,
-        );
+        Ok(RetiredPipeline {
+            vertices: patch.vertices,
+            storage: patch.storage,
+            default_storage: patch.default_storage,
+            feedback_storage: patch.feedback_storage,
+            layout: patch.layout,
+            applied: self.layout.clone(),
+        })
+    }
 
-        Self {
-            arena,
-            vertices,
+    /// Brings the pipeline up to date with the graph, rebinding only the nodes
+    /// that were inserted, edited or rewired since the pipeline was compiled.
+    ///
+    /// Untouched nodes keep their state (and the values of their sockets), so
+    /// the rest of the mix keeps playing as if nothing happened.
+    pub fn recompile(&mut self, graph: &Graph) -> Result<(), CompileError> {
+        let patch = self.editor().patch(graph)?;
+        if self.apply(patch).is_err() {
+            unreachable!("the patch was made from this very pipeline");
         }
+        Ok(())
+    }
+
+    /// Brings every node back to its initial state before the next run,
+    /// like when playback jumps to another point in time.
+    ///
+    /// Nodes see [`ProcessContext::reset`] set during that run, and feedback
+    /// connections read their socket's initial value again.
+    pub fn reset(&mut self) {
+        self.reset_pending = true;
+    }
+
+    /// Makes the pipeline run independent nodes on a pool of `threads` worker threads.
+    ///
+    /// Nodes still see exactly the same values as when running on a single thread,
+    /// so the results are identical — only the wall time changes. Passing `0` or `1`
+    /// goes back to running everything on the calling thread.
+    #[cfg(feature = "parallel")]
+    pub fn set_threads(&mut self, threads: usize) {
+        self.pool = (threads > 1).then(|| {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .expect("failed to spawn the pipeline's worker threads")
+        });
     }
 
     /// Runs the pipeline.
@@ -219,17 +1130,607 @@ impl GraphPipeline {
     ///
     /// Not sure if this _should_ be mutable. I guess since nodes have state that mutates
     /// when this runs, yeah, sure.
-    pub fn run(&mut self) {
+    ///
+    /// # Transport
+    ///
+    /// The pipeline doesn't keep time by itself. Call [`ProcessContext::advance`]
+    /// between runs to move on to the next block.
+    pub fn run(&mut self, context: &ProcessContext) {
+        let resets = self.layout.resets.load(Ordering::Acquire);
+        if resets != self.seen_resets {
+            self.seen_resets = resets;
+            self.reset_pending = true;
+        }
+
+        let reset_context;
+        let context = if std::mem::take(&mut self.reset_pending) {
+            for storage in self.feedback_storage.iter_mut() {
+                storage.reinitialize();
+            }
+            reset_context = ProcessContext {
+                reset: true,
+                ..*context
+            };
+            &reset_context
+        } else {
+            context
+        };
+
+        #[cfg(feature = "parallel")]
+        if let Some(pool) = &self.pool {
+            use rayon::iter::{IntoParallelRefMutIterator, ParallelIterator};
+
+            let vertices = &mut self.vertices;
+            let levels = &self.levels;
+            pool.install(|| {
+                for level in levels.iter() {
+                    match &mut vertices[level.clone()] {
+                        [vertex] => vertex(context),
+                        level => level.par_iter_mut().for_each(|vertex| vertex(context)),
+                    }
+                }
+            });
+            self.swap_feedback();
+            return;
+        }
+
         for vertex in self.vertices.iter_mut() {
-            vertex()
+            vertex(context)
+        }
+        self.swap_feedback();
+    }
+
+    /// After every run, each output that feeds a feedback connection is swapped
+    /// with its delayed copy, so those connections see this run's value on the next run.
+    fn swap_feedback(&mut self) {
+        for feedback in self.layout.feedback.iter() {
+            // SAFETY: Both allocations are alive for as long as the layout is, and have the same layout.
+            unsafe {
+                std::ptr::swap_nonoverlapping(
+                    feedback.output.as_ptr(),
+                    feedback.delayed.as_ptr(),
+                    feedback.size,
+                );
+            }
+        }
+    }
+}
+
+impl Drop for GraphPipeline {
+    fn drop(&mut self) {
+        // The vertices hold references into the storage, so they go first.
+        self.vertices.clear();
+    }
+}
+
+/// A node's processing function, bound to its sockets.
+type Vertex = Box<dyn FnMut(&ProcessContext) + Send>;
+
+/// A socket's storage, along with the function that drops its value.
+type SocketDrop = (NonNull<u8>, unsafe fn(NonNull<u8>));
+
+/// A socket's storage, along with the function that gives it its initial value.
+type SocketInit = (NonNull<u8>, unsafe fn(NonNull<u8>));
+
+/// Stands in for a vertex that was moved somewhere else. Boxing it doesn't allocate.
+fn moved_vertex() -> Vertex {
+    Box::new(|_| {})
+}
+
+/// Prepares patches for a [`GraphPipeline`], possibly on another thread.
+///
+/// ```ignore
+/// let editor = pipeline.editor();
+/// // Send `pipeline` to the audio thread...
+/// graph.insert(Reverb::default());
+/// let patch = editor.patch(&graph)?;
+/// // ...and on the audio thread, between two runs:
+/// let retired = pipeline.apply(patch)?;
+/// // Back on this thread, the next patch builds on the applied one.
+/// let editor = retired.editor();
+/// ```
+pub struct PipelineEditor {
+    /// The layout of the pipeline the patches are made for.
+    layout: Arc<PipelineLayout>,
+}
+
+impl PipelineEditor {
+    /// Prepares a patch that turns the pipeline into one compiled from `graph`,
+    /// for the same sinks.
+    ///
+    /// Nodes that weren't edited (see [`Graph::get_mut`]) and whose inputs still read
+    /// from the same places keep their bound function, and with it their state.
+    /// Everything else is bound here, so applying the patch is cheap.
+    ///
+    /// Making a patch doesn't change the editor, since the patch may never be applied.
+    /// Once it is, make the next patch with [`RetiredPipeline::editor`].
+    pub fn patch(&self, graph: &Graph) -> Result<PipelinePatch, CompileError> {
+        let old = &self.layout;
+        let sinks = old.sinks.clone().unwrap_or_else(|| graph.sinks());
+        if let Some(missing) = sinks.iter().find(|sink| !graph.nodes.contains_key(sink)) {
+            return Err(CompileError::NoSuchNode(*missing));
+        }
+        let (order, levels) = schedule(graph, &sinks)?;
+
+        let old_nodes: HashMap<NodeKey, usize> = old
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(index, bound)| (bound.key, index))
+            .collect();
+
+        // First, find (or make) the storage of every node, so that every output
+        // has an address before anything is bound.
+        let mut nodes = Vec::with_capacity(order.len());
+        let mut storage = Vec::with_capacity(order.len());
+        let mut kept_storage = Vec::new();
+        let mut default_storage = Vec::with_capacity(order.len());
+        let mut kept_default_storage = Vec::new();
+        let mut output_pointers = HashMap::<(NodeKey, SocketIndex), NonNull<u8>>::new();
+
+        for (new_index, &node_key) in order.iter().enumerate() {
+            let node = graph.nodes.get(&node_key).unwrap();
+            let outputs: Vec<SocketData> = (0..).map_while(|index| node.output_socket(index)).collect();
+            let inputs: Vec<SocketData> = (0..).map_while(|index| node.input_socket(index)).collect();
+            let optional = inputs.iter().map(|socket| socket.optional).collect();
+            let signature = StorageSignature {
+                outputs: outputs.iter().map(|socket| socket.type_id).collect(),
+                defaults: inputs
+                    .iter()
+                    .map(|socket| socket.default.is_some().then_some(socket.type_id))
+                    .collect(),
+            };
+
+            let revision = graph.revision(node_key);
+            let old_bound = old_nodes
+                .get(&node_key)
+                .map(|&old_index| (old_index, &old.nodes[old_index]));
+
+            let outputs = match old_bound.filter(|(_, bound)| bound.signature.outputs == signature.outputs) {
+                Some((old_index, bound)) => {
+                    kept_storage.push((new_index, old_index));
+                    storage.push(SocketBlock::empty());
+                    bound.outputs.clone()
+                }
+                None => {
+                    let sockets = outputs.into_iter().map(|socket| (socket, Fill::Init)).collect();
+                    let (block, pointers) = SocketBlock::new(sockets);
+                    storage.push(block);
+                    pointers
+                }
+            };
+
+            // Defaults can depend on the node's fields, so an edited node gets new ones.
+            let kept_defaults = old_bound.filter(|(_, bound)| {
+                bound.signature.defaults == signature.defaults && bound.revision == revision
+            });
+            let defaults = match kept_defaults {
+                Some((old_index, bound)) => {
+                    kept_default_storage.push((new_index, old_index));
+                    default_storage.push(SocketBlock::empty());
+                    bound.defaults.clone()
+                }
+                None => {
+                    let sockets = inputs
+                        .into_iter()
+                        .filter(|socket| socket.default.is_some())
+                        .map(|socket| (socket, Fill::Default))
+                        .collect();
+                    let (block, pointers) = SocketBlock::new(sockets);
+                    default_storage.push(block);
+
+                    let mut pointers = pointers.into_iter();
+                    signature
+                        .defaults
+                        .iter()
+                        .map(|default| default.and_then(|_| pointers.next()))
+                        .collect()
+                }
+            };
+
+            for (index, pointer) in outputs.iter().enumerate() {
+                output_pointers.insert((node_key, SocketIndex(index)), *pointer);
+            }
+            nodes.push(BoundNode {
+                key: node_key,
+                revision,
+                signature,
+                inputs: Vec::new(),
+                outputs,
+                defaults,
+                optional,
+            });
+        }
+
+        // Feedback connections read from a copy of the output that's one run behind.
+        // Keeping that copy keeps whatever is circulating in the loop.
+        let old_feedback: HashMap<(NodeKey, SocketIndex), usize> = old
+            .feedback
+            .iter()
+            .enumerate()
+            .map(|(index, feedback)| (feedback.from, index))
+            .collect();
+        let scheduled: HashSet<NodeKey> = order.iter().copied().collect();
+        let mut feedback_sources: Vec<(NodeKey, SocketIndex)> = graph
+            .edges
+            .iter()
+            .filter(|((to_node, _), edge)| {
+                edge.kind == ConnectionKind::Feedback && scheduled.contains(to_node)
+            })
+            .map(|(_, edge)| (edge.node, edge.socket))
+            .collect();
+        feedback_sources.sort();
+        feedback_sources.dedup();
+
+        let mut feedback = Vec::with_capacity(feedback_sources.len());
+        let mut feedback_storage = Vec::with_capacity(feedback_sources.len());
+        let mut kept_feedback = Vec::new();
+        let mut delayed_pointers = HashMap::<(NodeKey, SocketIndex), NonNull<u8>>::new();
+
+        for (new_index, from) in feedback_sources.into_iter().enumerate() {
+            let socket = graph.nodes[&from.0].output_socket(from.1.0).unwrap();
+            if socket.uninit {
+                return Err(CompileError::UninitFeedback {
+                    node: from.0,
+                    socket: from.1.0,
+                });
+            }
+            let size = socket.layout.size();
+            let output = output_pointers[&from];
+            let old_index = old_feedback
+                .get(&from)
+                .copied()
+                .filter(|&old_index| old.feedback[old_index].output == output);
+
+            let delayed = match old_index {
+                Some(old_index) => {
+                    kept_feedback.push((new_index, old_index));
+                    feedback_storage.push(SocketBlock::empty());
+                    old.feedback[old_index].delayed
+                }
+                None => {
+                    let (block, pointers) = SocketBlock::new(vec![(socket, Fill::Init)]);
+                    feedback_storage.push(block);
+                    pointers[0]
+                }
+            };
+
+            delayed_pointers.insert(from, delayed);
+            feedback.push(BoundFeedback {
+                from,
+                output,
+                delayed,
+                size,
+            });
+        }
+
+        // Then bind every node whose function can't be kept.
+        let mut vertices = Vec::with_capacity(order.len());
+        let mut kept_vertices = Vec::new();
+
+        for (new_index, bound) in nodes.iter_mut().enumerate() {
+            let node_key = bound.key;
+
+            // Inputs are bound strictly in socket order.
+            for (input_index, (default, optional)) in bound.defaults.iter().zip(&bound.optional).enumerate() {
+                let pointer = match graph.edges.get(&(node_key, SocketIndex(input_index))) {
+                    Some(edge) if edge.kind == ConnectionKind::Direct => {
+                        output_pointers[&(edge.node, edge.socket)]
+                    }
+                    Some(edge) => delayed_pointers[&(edge.node, edge.socket)],
+                    None => default.or(optional.then(unconnected_input)).ok_or(CompileError::UnconnectedInput {
+                        node: node_key,
+                        socket: input_index,
+                    })?,
+                };
+                bound.inputs.push(pointer);
+            }
+
+            let kept = old_nodes
+                .get(&node_key)
+                .map(|&old_index| (old_index, &old.nodes[old_index]))
+                .filter(|(_, old_bound)| {
+                    old_bound.revision == bound.revision
+                        && old_bound.inputs == bound.inputs
+                        && old_bound.outputs == bound.outputs
+                });
+
+            match kept {
+                Some((old_index, _)) => {
+                    kept_vertices.push((new_index, old_index));
+                    vertices.push(moved_vertex());
+                }
+                None => {
+                    let node = graph.nodes.get(&node_key).unwrap();
+                    let mut parameter_iterator =
+                        bound.inputs.iter().chain(bound.outputs.iter()).copied();
+                    vertices.push(node.bind_parameters(&mut parameter_iterator));
+                }
+            }
+        }
+
+        let layout = Arc::new(PipelineLayout {
+            version: next_layout_version(),
+            sinks: old.sinks.clone(),
+            nodes,
+            feedback,
+            resets: graph.resets.clone(),
+        });
+        Ok(PipelinePatch {
+            base: old.version,
+            layout,
+            vertices,
+            kept_vertices,
+            storage,
+            kept_storage,
+            default_storage,
+            kept_default_storage,
+            feedback_storage,
+            kept_feedback,
+            levels,
+        })
+    }
+}
+
+/// A set of changes to a [`GraphPipeline`], ready to be [applied](GraphPipeline::apply).
+///
+/// Made by a [`PipelineEditor`].
+pub struct PipelinePatch {
+    /// The version of the layout this patch applies to.
+    base: u64,
+    layout: Arc<PipelineLayout>,
+    /// The new vertices, with placeholders where an old vertex is kept.
+    vertices: Vec<Vertex>,
+    /// Pairs of (new index, old index) of the vertices that are kept.
+    kept_vertices: Vec<(usize, usize)>,
+    storage: Vec<SocketBlock>,
+    kept_storage: Vec<(usize, usize)>,
+    default_storage: Vec<SocketBlock>,
+    kept_default_storage: Vec<(usize, usize)>,
+    feedback_storage: Vec<SocketBlock>,
+    kept_feedback: Vec<(usize, usize)>,
+    levels: Vec<Range<usize>>,
+}
+
+// SAFETY: The pointers only point into socket storage owned by the patch or its pipeline.
+unsafe impl Send for PipelinePatch {}
+
+impl PipelinePatch {
+    /// How many nodes had to be bound again (or for the first time) for this patch.
+    pub fn rebound_nodes(&self) -> usize {
+        self.vertices.len() - self.kept_vertices.len()
+    }
+}
+
+/// Whatever a [`PipelinePatch`] replaced, returned by [`GraphPipeline::apply`].
+///
+/// Dropping it frees the old functions and storage, so do that off the audio thread.
+pub struct RetiredPipeline {
+    // Fields drop in order: the vertices hold references into the storage, so they go first.
+    vertices: Vec<Vertex>,
+    storage: Vec<SocketBlock>,
+    default_storage: Vec<SocketBlock>,
+    feedback_storage: Vec<SocketBlock>,
+    layout: Arc<PipelineLayout>,
+    /// The layout of the pipeline as the patch left it.
+    applied: Arc<PipelineLayout>,
+}
+
+// SAFETY: Same as for `GraphPipeline`.
+unsafe impl Send for RetiredPipeline {}
+
+impl RetiredPipeline {
+    /// Returns an editor for the pipeline as the patch left it, to make the next patch with.
+    pub fn editor(&self) -> PipelineEditor {
+        PipelineEditor {
+            layout: self.applied.clone(),
+        }
+    }
+}
+
+/// Everything a pipeline bound, kept so the next patch knows what it can reuse.
+struct PipelineLayout {
+    /// Unique among all layouts, so a patch can tell if it's applied to the right pipeline.
+    version: u64,
+    /// The sinks the pipeline drives, or `None` for every sink of the graph.
+    sinks: Option<Vec<NodeKey>>,
+    /// The nodes, in the order they run.
+    nodes: Vec<BoundNode>,
+    feedback: Vec<BoundFeedback>,
+    /// How many times the graph the layout was made from was reset.
+    resets: Arc<AtomicU64>,
+}
+
+// SAFETY: The pointers are only ever dereferenced by the pipeline that owns their storage.
+unsafe impl Send for PipelineLayout {}
+unsafe impl Sync for PipelineLayout {}
+
+fn next_layout_version() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+/// A node as it was bound into a pipeline.
+struct BoundNode {
+    key: NodeKey,
+    revision: u64,
+    signature: StorageSignature,
+    /// Where each input socket reads from.
+    inputs: Vec<NonNull<u8>>,
+    /// Where each output socket writes to.
+    outputs: Vec<NonNull<u8>>,
+    /// The storage of each input socket's default value, if it has one.
+    defaults: Vec<Option<NonNull<u8>>>,
+    /// Whether each input socket may be left unconnected without a default.
+    optional: Vec<bool>,
+}
+
+/// What unconnected optional inputs point at (see [`SocketData::optional`]).
+///
+/// No socket's storage can ever live at the address of a static, so this can't be mistaken for one.
+pub(crate) fn unconnected_input() -> NonNull<u8> {
+    static UNCONNECTED: u8 = 0;
+    NonNull::from(&UNCONNECTED)
+}
+
+/// The shape of a node's storage: if it doesn't change, the storage can be kept
+/// (its defaults, only if the node wasn't edited either).
+#[derive(PartialEq, Eq)]
+struct StorageSignature {
+    outputs: Vec<TypeId>,
+    defaults: Vec<Option<TypeId>>,
+}
+
+/// An output read by some feedback connection, along with its delayed copy.
+struct BoundFeedback {
+    from: (NodeKey, SocketIndex),
+    output: NonNull<u8>,
+    delayed: NonNull<u8>,
+    size: usize,
+}
+
+/// How to give a socket its first value.
+enum Fill {
+    /// With [`SocketData`]'s initializer, like outputs.
+    Init,
+    /// With [`SocketData::with_default`]'s value, like unconnected inputs.
+    Default,
+}
+
+/// A single allocation holding the values of several sockets.
+struct SocketBlock {
+    pointer: NonNull<u8>,
+    layout: Layout,
+    /// Every socket whose value needs dropping, with its drop function.
+    drops: Vec<SocketDrop>,
+    /// Every socket that was filled by its initializer, with that initializer.
+    inits: Vec<SocketInit>,
+}
+
+impl SocketBlock {
+    /// A block with no sockets. Making one doesn't allocate.
+    fn empty() -> Self {
+        Self {
+            pointer: NonNull::dangling(),
+            layout: Layout::new::<()>(),
+            drops: Vec::new(),
+            inits: Vec::new(),
         }
     }
+
+    /// Allocates storage for the sockets and initializes it.
+    ///
+    /// Returns the address of each socket, in order.
+    fn new(sockets: Vec<(SocketData, Fill)>) -> (Self, Vec<NonNull<u8>>) {
+        let mut layout = Layout::new::<()>();
+        let mut offsets = Vec::with_capacity(sockets.len());
+        for (socket, _) in sockets.iter() {
+            let (extended, offset) = layout.extend(socket.layout).expect("socket storage is too large");
+            layout = extended;
+            offsets.push(offset);
+        }
+
+        let pointer = if layout.size() == 0 {
+            // SAFETY: An alignment is never zero.
+            unsafe { NonNull::new_unchecked(std::ptr::without_provenance_mut(layout.align())) }
+        } else {
+            // SAFETY: The layout isn't zero-sized.
+            let pointer = unsafe { std::alloc::alloc(layout) };
+            NonNull::new(pointer).unwrap_or_else(|| std::alloc::handle_alloc_error(layout))
+        };
+
+        let mut block = Self {
+            pointer,
+            layout,
+            drops: Vec::new(),
+            inits: Vec::new(),
+        };
+        let mut pointers = Vec::with_capacity(sockets.len());
+        for ((socket, fill), offset) in sockets.into_iter().zip(offsets) {
+            // SAFETY: The offset comes from extending the block's layout with this socket's.
+            let pointer = unsafe { block.pointer.add(offset) };
+            match fill {
+                Fill::Init if socket.uninit => {}
+                Fill::Init => {
+                    let init = socket.init.unwrap_or_else(|| {
+                        panic!(
+                            "output socket of type `{}` was described with `SocketData::input`",
+                            socket.type_name
+                        )
+                    });
+                    // SAFETY: The pointer is aligned for, and as large as, the socket's layout.
+                    unsafe { init(pointer) };
+                    block.inits.push((pointer, init));
+                }
+                Fill::Default => (socket.default.as_ref().unwrap())(pointer),
+            }
+            if let Some(drop) = socket.drop {
+                block.drops.push((pointer, drop));
+            }
+            pointers.push(pointer);
+        }
+
+        (block, pointers)
+    }
 }
 
-fn allocate_aligned(buffer: &mut Vec<u8>, size: usize, align: usize) -> *mut u8 {
-    let current_pointer = buffer.as_mut_ptr().wrapping_byte_add(buffer.len());
-    let padding = align - current_pointer as usize % align;
-    let current_pointer = current_pointer.wrapping_add(padding);
-    buffer.extend(std::iter::repeat(0x00).take(padding + size));
-    current_pointer
-}
\ No newline at end of file
+impl SocketBlock {
+    /// Puts the sockets filled by their initializer back to their initial value.
+    fn reinitialize(&mut self) {
+        for &(pointer, init) in self.inits.iter() {
+            if let Some(&(_, drop)) = self.drops.iter().find(|(dropped, _)| *dropped == pointer) {
+                // SAFETY: The socket holds a valid value, which is replaced right away.
+                unsafe { drop(pointer) }
+            }
+            // SAFETY: Same as when the block was made.
+            unsafe { init(pointer) }
+        }
+    }
+}
+
+impl Drop for SocketBlock {
+    fn drop(&mut self) {
+        for &(pointer, drop) in self.drops.iter() {
+            // SAFETY: Every socket was initialized when the block was made,
+            // and nothing can read from it anymore.
+            unsafe { drop(pointer) }
+        }
+        if self.layout.size() != 0 {
+            // SAFETY: The block was allocated with this very layout.
+            unsafe { std::alloc::dealloc(self.pointer.as_ptr(), self.layout) }
+        }
+    }
+}
+
+/// Orders the nodes the sinks depend on, and groups them by how deep they are in the graph.
+///
+/// Nodes at the same depth never depend on each other, so each group may run in parallel.
+fn schedule(graph: &Graph, sinks: &[NodeKey]) -> Result<(Vec<NodeKey>, Vec<Range<usize>>), CompileError> {
+    let nodes = graph.upstream_of(sinks);
+    let mut order = graph
+        .topological_order(&nodes)
+        .map_err(CompileError::Cycle)?;
+
+    let mut depths = HashMap::<NodeKey, usize>::with_capacity(order.len());
+    for node in order.iter() {
+        let depth = graph
+            .edges
+            .iter()
+            .filter(|((to_node, _), edge)| to_node == node && edge.kind == ConnectionKind::Direct)
+            .map(|(_, edge)| depths[&edge.node] + 1)
+            .max()
+            .unwrap_or(0);
+        depths.insert(*node, depth);
+    }
+    order.sort_by_key(|node| depths[node]);
+
+    let mut levels: Vec<Range<usize>> = Vec::new();
+    for (index, node) in order.iter().enumerate() {
+        match levels.last_mut() {
+            Some(level) if depths[&order[level.start]] == depths[node] => level.end = index + 1,
+            _ => levels.push(index..index + 1),
+        }
+    }
+
+    Ok((order, levels))
+}
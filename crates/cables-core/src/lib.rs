@@ -7,20 +7,31 @@ use std::{
 use std::hint::black_box;
 use std::ptr::NonNull;
 use graph::Graph;
-use nodes::{NumSource, Sum, YellNum};
-use crate::graph::{GraphPipeline, Node};
-use crate::nodes::Double;
+use nodes::{NumSource, Subtract, Sum, YellNum};
+use std::sync::{Arc, Mutex};
+use crate::audio::{AudioBlock, DEFAULT_BLOCK_LEN};
+use crate::graph::{
+    CompileError, ConnectError, Connection, ConnectionKind, GraphPipeline, Node, NodeKey, ProcessContext,
+    SocketData,
+};
+use crate::nodes::{Double, Gain, Mix, SineOscillator};
 
 pub mod old;
 pub mod graph;
 pub mod nodes;
+pub mod audio;
+pub mod param;
+pub mod registry;
+pub mod descriptor;
+#[cfg(feature = "serde")]
+pub mod document;
 
 #[test]
 pub fn speed() {
     let mut graph = Graph::new();
 
-    let a = graph.insert(NumSource { value: 2.0 });
-    let b = graph.insert(NumSource { value: 1.0 });
+    let a = graph.insert(NumSource::new(2.0));
+    let b = graph.insert(NumSource::new(1.0));
     let c = graph.insert(Sum);
     let d = graph.insert(YellNum);
 
@@ -28,12 +39,12 @@ pub fn speed() {
     graph.connect(b, 0, c, 1).unwrap();
     graph.connect(c, 0, d, 0).unwrap();
 
-    let mut pipeline = graph.compile(d, 0);
+    let mut pipeline = graph.compile_sinks(&[d]).unwrap();
 
     let iterations = 1000;
     let before = black_box(Instant::now());
     for _ in 0..iterations {
-        pipeline.run();
+        pipeline.run(&ProcessContext::default());
     }
     println!("Took {:?}", before.elapsed().div_f64(iterations as f64));
     std::io::stdout().flush().unwrap();
@@ -43,8 +54,8 @@ pub fn speed() {
 pub fn compile_speed() {
     let mut graph = Graph::new();
 
-    let a = graph.insert(NumSource { value: 2.0 });
-    let b = graph.insert(NumSource { value: 1.0 });
+    let a = graph.insert(NumSource::new(2.0));
+    let b = graph.insert(NumSource::new(1.0));
     let c = graph.insert(Sum);
     let d = graph.insert(YellNum);
 
@@ -55,7 +66,7 @@ pub fn compile_speed() {
     let iterations = 100;
     let before = Instant::now();
     for _ in 0..iterations {
-        let mut pipeline = graph.compile(d, 0);
+        let mut pipeline = graph.compile_sinks(&[d]).unwrap();
     }
     println!("Took {:?}", before.elapsed().div_f64(iterations as f64));
     std::io::stdout().flush().unwrap();
@@ -84,7 +95,7 @@ fn diamond() {
     //      │YellNum│4
     //      └───────┘
 
-    let source = graph.insert(NumSource { value: 3.5 });
+    let source = graph.insert(NumSource::new(3.5));
     let doubler_l = graph.insert(Double);
     let doubler_r = graph.insert(Double);
     let sum = graph.insert(Sum);
@@ -96,24 +107,747 @@ fn diamond() {
     graph.connect(doubler_r, 0, sum, 1).unwrap();
     graph.connect(sum, 0, output, 0).unwrap();
 
-    let mut p = GraphPipeline::from_graph(&graph, output, 0);
+    let mut p = GraphPipeline::from_graph(&graph, &[output]).unwrap();
 
     let iterations = 44100;
     let before = Instant::now();
     for _ in 0..iterations {
-        black_box(p.run());
+        p.run(&ProcessContext::default());
+        black_box(&mut p);
     }
     let elapsed = before.elapsed();
     println!("Total {:?} ({:?} per run)", elapsed, elapsed.div_f64(iterations as f64));
     std::io::stdout().flush().unwrap();
 }
 
+#[test]
+fn connect_validation() {
+    struct Flag;
+
+    impl Node for Flag {
+        fn bind_parameters<'pip>(&self, parameters: &mut dyn Iterator<Item=NonNull<u8>>) -> Box<dyn FnMut(&ProcessContext) + Send + 'pip> {
+            let out = as_output::<bool>(parameters.next().unwrap());
+            Box::new(move |_| *out = true)
+        }
+
+        fn input_socket(&self, _socket_index: usize) -> Option<SocketData> {
+            None
+        }
+
+        fn output_socket(&self, socket_index: usize) -> Option<SocketData> {
+            match socket_index {
+                0 => Some(SocketData::new::<bool>()),
+                _ => None,
+            }
+        }
+    }
+
+    let mut graph = Graph::new();
+
+    let a = graph.insert(NumSource::new(2.0));
+    let b = graph.insert(NumSource::new(1.0));
+    let flag = graph.insert(Flag);
+    let sum = graph.insert(Sum);
+
+    assert!(matches!(
+        graph.connect(a, 1, sum, 0),
+        Err(ConnectError::NoSuchOutputSocket { socket: 1, .. })
+    ));
+    assert!(matches!(
+        graph.connect(a, 0, sum, 2),
+        Err(ConnectError::NoSuchInputSocket { socket: 2, .. })
+    ));
+    assert!(matches!(
+        graph.connect(flag, 0, sum, 0),
+        Err(ConnectError::TypeMismatch { output_type: "bool", input_type: "f64" })
+    ));
+
+    graph.connect(a, 0, sum, 0).unwrap();
+    assert_eq!(
+        graph.connect(b, 0, sum, 0),
+        Err(ConnectError::InputAlreadyConnected { connected_node: a, connected_socket: 0 })
+    );
+
+    // Sockets can also be picked by name, names only match on the right side.
+    graph.connect(b, "out", sum, "b").unwrap();
+    assert_eq!(graph.edges_into(sum)[1].input_socket, 1);
+    assert!(matches!(
+        graph.connect(sum, "a", flag, 0),
+        Err(ConnectError::NoSuchSocketName { .. })
+    ));
+}
+
+/// Test node that exposes the last value it received.
+#[cfg(test)]
+struct Probe<T>(Arc<Mutex<T>>);
+
+#[cfg(test)]
+impl<T: Clone + Send + Sync + 'static> Node for Probe<T> {
+    fn bind_parameters<'pip>(&self, parameters: &mut dyn Iterator<Item=NonNull<u8>>) -> Box<dyn FnMut(&ProcessContext) + Send + 'pip> {
+        let seen = self.0.clone();
+        let in1 = as_input::<T>(parameters.next().unwrap());
+        Box::new(move |_| *seen.lock().unwrap() = in1.clone())
+    }
+
+    fn input_socket(&self, socket_index: usize) -> Option<SocketData> {
+        match socket_index {
+            0 => Some(SocketData::input::<T>()),
+            _ => None,
+        }
+    }
+
+    fn output_socket(&self, _socket_index: usize) -> Option<SocketData> {
+        None
+    }
+}
+
+#[test]
+fn multiple_sinks_share_upstream() {
+    let mut graph = Graph::new();
+
+    let source = graph.insert(NumSource::new(1.5));
+    let double = graph.insert(Double);
+    let preview = Arc::new(Mutex::new(0.0));
+    let meter = Arc::new(Mutex::new(0.0));
+    let preview_probe = graph.insert(Probe(preview.clone()));
+    let meter_probe = graph.insert(Probe(meter.clone()));
+    let exporter = graph.insert(YellNum);
+
+    graph.connect(source, 0, double, 0).unwrap();
+    graph.connect(double, 0, preview_probe, 0).unwrap();
+    graph.connect(double, 0, meter_probe, 0).unwrap();
+    graph.connect(double, 0, exporter, 0).unwrap();
+
+    assert_eq!(graph.sinks(), vec![preview_probe, meter_probe, exporter]);
+
+    let mut pipeline = graph.compile_all().unwrap();
+    pipeline.run(&ProcessContext::default());
+    assert_eq!(*preview.lock().unwrap(), 3.0);
+    assert_eq!(*meter.lock().unwrap(), 3.0);
+
+    *meter.lock().unwrap() = 0.0;
+    let mut pipeline = graph.compile_sinks(&[meter_probe]).unwrap();
+    pipeline.run(&ProcessContext::default());
+    assert_eq!(*meter.lock().unwrap(), 3.0);
+}
+
+#[test]
+fn graph_editing() {
+    let mut graph = Graph::new();
+
+    let a = graph.insert(NumSource::new(2.0));
+    let b = graph.insert(NumSource::new(1.0));
+    let sum = graph.insert(Sum);
+    let seen = Arc::new(Mutex::new(0.0));
+    let probe = graph.insert(Probe(seen.clone()));
+
+    graph.connect(a, 0, sum, 0).unwrap();
+    graph.connect(b, 0, sum, 1).unwrap();
+    graph.connect(sum, 0, probe, 0).unwrap();
+
+    assert_eq!(graph.nodes().map(|(key, _)| key).collect::<Vec<_>>(), vec![a, b, sum, probe]);
+    assert_eq!(
+        graph.edges_into(sum),
+        vec![
+            Connection { output_node: a, output_socket: 0, input_node: sum, input_socket: 0, kind: ConnectionKind::Direct },
+            Connection { output_node: b, output_socket: 0, input_node: sum, input_socket: 1, kind: ConnectionKind::Direct },
+        ]
+    );
+    assert_eq!(graph.edges_from(sum).len(), 1);
+
+    // Typed access.
+    assert!(graph.get::<Sum>(a).is_none());
+    graph.get_mut::<NumSource>(a).unwrap().value.set(10.0);
+    assert_eq!(graph.get::<NumSource>(a).unwrap().value.get(), 10.0);
+
+    // Rewire `b`'s slot to a new node; the other keys must stay valid.
+    let disconnected = graph.disconnect(sum, 1).unwrap();
+    assert_eq!(disconnected.output_node, b);
+    assert!(graph.disconnect(sum, 1).is_none());
+    assert!(graph.remove(b).is_some());
+    assert!(!graph.contains(b));
+    let c = graph.insert(NumSource::new(5.0));
+    assert_ne!(c, b);
+    graph.connect(c, 0, sum, 1).unwrap();
+
+    graph.compile_sinks(&[probe]).unwrap().run(&ProcessContext::default());
+    assert_eq!(*seen.lock().unwrap(), 15.0);
+
+    // Removing a node takes its connections with it.
+    graph.remove(sum);
+    assert!(graph.edges_from(a).is_empty());
+    assert!(graph.edges_into(probe).is_empty());
+}
+
+#[test]
+fn block_processing() {
+    /// Test node that exposes the last block it received.
+    struct BlockProbe(Arc<Mutex<AudioBlock<8>>>);
+
+    impl Node for BlockProbe {
+        fn bind_parameters<'pip>(&self, parameters: &mut dyn Iterator<Item=NonNull<u8>>) -> Box<dyn FnMut(&ProcessContext) + Send + 'pip> {
+            let seen = self.0.clone();
+            let in1 = as_input::<AudioBlock>(parameters.next().unwrap());
+            Box::new(move |context| {
+                let mut block = AudioBlock::default();
+                block.frames_mut(context).copy_from_slice(in1.frames(context));
+                *seen.lock().unwrap() = block;
+            })
+        }
+
+        fn input_socket(&self, socket_index: usize) -> Option<SocketData> {
+            match socket_index {
+                0 => Some(SocketData::new::<AudioBlock>()),
+                _ => None,
+            }
+        }
+
+        fn output_socket(&self, _socket_index: usize) -> Option<SocketData> {
+            None
+        }
+    }
+
+    let mut graph = Graph::new();
+
+    // A quarter of a cycle every 2 frames.
+    let oscillator = graph.insert(SineOscillator { frequency: 1.0 });
+    let gain = graph.insert(Gain::new(0.5));
+    let seen = Arc::new(Mutex::new(AudioBlock::default()));
+    let probe = graph.insert(BlockProbe(seen.clone()));
+
+    graph.connect(oscillator, 0, gain, 0).unwrap();
+    graph.connect(gain, 0, probe, 0).unwrap();
+
+    let mut pipeline = graph.compile_sinks(&[probe]).unwrap();
+    let mut context = ProcessContext::new(8, 4);
+
+    let expected = [0.0, 0.35355338, 0.5, 0.35355338, 0.0, -0.35355338, -0.5, -0.35355338];
+    for block in expected.chunks(4) {
+        pipeline.run(&context);
+        let seen = *seen.lock().unwrap();
+        for (seen, expected) in seen.frames(&context).iter().zip(block) {
+            assert!((seen - expected).abs() < 1e-6, "{seen} != {expected}");
+        }
+        context.advance();
+    }
+    assert_eq!(context.frame, 8);
+}
+
+#[test]
+#[should_panic(expected = "don't fit")]
+fn runs_longer_than_blocks() {
+    let block = AudioBlock::<8>::default();
+    block.frames(&ProcessContext::new(44100, 16));
+}
+
+#[test]
+fn time_without_sample_rate() {
+    let mut context = ProcessContext::new(0, 4);
+    context.advance();
+    assert_eq!(context.time(), 0.0);
+
+    context.sample_rate = 8;
+    assert_eq!(context.time(), 0.5);
+}
+
+#[test]
+fn cycles_are_rejected() {
+    let mut graph = Graph::new();
+
+    let source = graph.insert(NumSource::new(1.0));
+    let a = graph.insert(Sum);
+    let b = graph.insert(Double);
+    let output = graph.insert(YellNum);
+
+    graph.connect(source, 0, a, 0).unwrap();
+    graph.connect(a, 0, b, 0).unwrap();
+    graph.connect(b, 0, a, 1).unwrap();
+    graph.connect(b, 0, output, 0).unwrap();
+
+    assert_eq!(graph.compile_sinks(&[output]).err(), Some(CompileError::Cycle(vec![a, b])));
+}
+
+#[test]
+fn compile_checks_sink_socket() {
+    let mut graph = Graph::new();
+    let source = graph.insert(NumSource::new(1.0));
+
+    assert!(graph.compile(source, 0).is_ok());
+    assert_eq!(
+        graph.compile(source, 1).err(),
+        Some(CompileError::NoSuchSocket { node: source, socket: 1 })
+    );
+}
+
+#[test]
+fn feedback_reads_previous_run() {
+    let mut graph = Graph::new();
+
+    // A counter: every run adds one to what the sum produced last run.
+    let one = graph.insert(NumSource::new(1.0));
+    let sum = graph.insert(Sum);
+    let seen = Arc::new(Mutex::new(0.0));
+    let probe = graph.insert(Probe(seen.clone()));
+
+    graph.connect(one, 0, sum, 0).unwrap();
+    graph.connect_feedback(sum, 0, sum, 1).unwrap();
+    graph.connect(sum, 0, probe, 0).unwrap();
+
+    let mut pipeline = graph.compile_sinks(&[probe]).unwrap();
+    for expected in [1.0, 2.0, 3.0, 4.0] {
+        pipeline.run(&ProcessContext::default());
+        assert_eq!(*seen.lock().unwrap(), expected);
+    }
+
+    // Resetting empties the loop.
+    pipeline.reset();
+    pipeline.run(&ProcessContext::default());
+    assert_eq!(*seen.lock().unwrap(), 1.0);
+}
+
+#[test]
+fn inputs_bind_in_socket_order() {
+    // Edges live in a hash map, so try a few fresh graphs to shake out any ordering luck.
+    for _ in 0..16 {
+        let mut graph = Graph::new();
+
+        let five = graph.insert(NumSource::new(5.0));
+        let two = graph.insert(NumSource::new(2.0));
+        let subtract = graph.insert(Subtract);
+        let seen = Arc::new(Mutex::new(0.0));
+        let probe = graph.insert(Probe(seen.clone()));
+
+        graph.connect(two, 0, subtract, 1).unwrap();
+        graph.connect(five, 0, subtract, 0).unwrap();
+        graph.connect(subtract, 0, probe, 0).unwrap();
+
+        graph.compile_sinks(&[probe]).unwrap().run(&ProcessContext::default());
+        assert_eq!(*seen.lock().unwrap(), 3.0);
+    }
+}
+
+#[test]
+fn unconnected_inputs() {
+    let mut graph = Graph::new();
+
+    let five = graph.insert(NumSource::new(5.0));
+    let subtract = graph.insert(Subtract);
+    let seen = Arc::new(Mutex::new(0.0));
+    let probe = graph.insert(Probe(seen.clone()));
+
+    graph.connect(subtract, 0, probe, 0).unwrap();
+    assert_eq!(
+        graph.compile_sinks(&[probe]).err(),
+        Some(CompileError::UnconnectedInput { node: subtract, socket: 0 })
+    );
+
+    // The subtrahend declares a default, so it may be left alone.
+    graph.connect(five, 0, subtract, 0).unwrap();
+    graph.compile_sinks(&[probe]).unwrap().run(&ProcessContext::default());
+    assert_eq!(*seen.lock().unwrap(), 5.0);
+}
+
+#[test]
+fn socket_storage_is_initialized_and_dropped() {
+    use std::sync::atomic::{AtomicIsize, Ordering};
+
+    static LIVE: AtomicIsize = AtomicIsize::new(0);
+
+    /// Keeps count of how many of it are alive.
+    struct Counted(Vec<f64>);
+
+    impl Default for Counted {
+        fn default() -> Self {
+            LIVE.fetch_add(1, Ordering::SeqCst);
+            Counted(vec![0.0; 16])
+        }
+    }
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            LIVE.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    struct Spawner;
+
+    impl Node for Spawner {
+        fn bind_parameters<'pip>(&self, parameters: &mut dyn Iterator<Item=NonNull<u8>>) -> Box<dyn FnMut(&ProcessContext) + Send + 'pip> {
+            let out = as_output::<Counted>(parameters.next().unwrap());
+            Box::new(move |_| *out = Counted::default())
+        }
+
+        fn input_socket(&self, _socket_index: usize) -> Option<SocketData> {
+            None
+        }
+
+        fn output_socket(&self, socket_index: usize) -> Option<SocketData> {
+            match socket_index {
+                0 => Some(SocketData::new::<Counted>()),
+                _ => None,
+            }
+        }
+    }
+
+    let mut graph = Graph::new();
+    let spawner = graph.insert(Spawner);
+
+    let mut pipeline = graph.compile(spawner, 0).unwrap();
+    assert_eq!(LIVE.load(Ordering::SeqCst), 1);
+    for _ in 0..3 {
+        pipeline.run(&ProcessContext::default());
+    }
+    assert_eq!(LIVE.load(Ordering::SeqCst), 1);
+    drop(pipeline);
+    assert_eq!(LIVE.load(Ordering::SeqCst), 0);
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn wide_mix_serial_and_parallel() {
+    //  32 × (SineOscillator → Gain), summed pairwise by a tree of `Mix` nodes.
+    let mut graph = Graph::new();
+
+    let mut layer: Vec<_> = (0..32)
+        .map(|voice| {
+            let oscillator = graph.insert(SineOscillator { frequency: 55.0 * (voice + 1) as f64 });
+            let gain = graph.insert(Gain::new(1.0 / 32.0));
+            graph.connect(oscillator, 0, gain, 0).unwrap();
+            gain
+        })
+        .collect();
+    while layer.len() > 1 {
+        layer = layer
+            .chunks(2)
+            .map(|pair| {
+                let mix = graph.insert(Mix);
+                graph.connect(pair[0], 0, mix, 0).unwrap();
+                graph.connect(pair[1], 0, mix, 1).unwrap();
+                mix
+            })
+            .collect();
+    }
+    let seen = Arc::new(Mutex::new(AudioBlock::<DEFAULT_BLOCK_LEN>::default()));
+    let probe = graph.insert(Probe(seen.clone()));
+    graph.connect(layer[0], 0, probe, 0).unwrap();
+
+    let mut render = |threads: usize| {
+        let mut pipeline = graph.compile_sinks(&[probe]).unwrap();
+        pipeline.set_threads(threads);
+
+        let mut context = ProcessContext::new(48000, 64);
+        let mut blocks = Vec::new();
+        let before = Instant::now();
+        for _ in 0..1000 {
+            pipeline.run(&context);
+            blocks.push(seen.lock().unwrap().map(f32::to_bits));
+            context.advance();
+        }
+        let elapsed = before.elapsed();
+        println!("{threads} thread(s): total {:?} ({:?} per block)", elapsed, elapsed.div_f64(1000.0));
+        blocks
+    };
+
+    let serial = render(1);
+    let parallel = render(4);
+    assert!(serial == parallel, "parallel render differs from the serial one");
+    std::io::stdout().flush().unwrap();
+}
+
 #[inline]
 pub fn as_input<'a, T>(ptr: NonNull<u8>) -> &'a T {
     unsafe { ptr.cast::<T>().as_ref() }
 }
 
+/// Like [`as_input`], for inputs described with [`SocketData::optional`].
+///
+/// Returns `None` if the input wasn't connected to anything.
+#[inline]
+pub fn as_optional_input<'a, T>(ptr: NonNull<u8>) -> Option<&'a T> {
+    (ptr != graph::unconnected_input()).then(|| as_input(ptr))
+}
+
 #[inline]
 pub fn as_output<'a, T>(ptr: NonNull<u8>) -> &'a mut T {
     unsafe { ptr.cast::<T>().as_mut() }
-}
\ No newline at end of file
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn document_round_trip() {
+    use crate::document::GraphDocument;
+    use crate::registry::NodeRegistry;
+
+    let registry = NodeRegistry::with_builtin_nodes();
+    let mut graph = Graph::new();
+
+    let a = graph.insert(NumSource::new(2.5));
+    let b = graph.insert(NumSource::new(1.0));
+    let sub = graph.insert(Subtract);
+    graph.connect(a, 0, sub, 0).unwrap();
+    graph.connect(b, 0, sub, 1).unwrap();
+    graph.set_position(sub, [120.0, 40.0]);
+    // Leave a hole in the keys, loading must keep them as they were.
+    graph.remove(b);
+    let c = graph.insert(NumSource::new(4.0));
+    graph.connect(c, 0, sub, 1).unwrap();
+
+    let document = GraphDocument::from_graph(&graph, &registry).unwrap();
+    let text = toml::to_string(&document).unwrap();
+    let loaded: GraphDocument = toml::from_str(&text).unwrap();
+    assert_eq!(loaded, document);
+
+    let mut restored = loaded.to_graph(&registry).unwrap();
+    assert_eq!(restored.get::<NumSource>(c).unwrap().value.get(), 4.0);
+    assert_eq!(restored.position(sub), Some([120.0, 40.0]));
+    assert_eq!(restored.edges_into(sub), graph.edges_into(sub));
+
+    let seen = Arc::new(Mutex::new(0.0));
+    let probe = restored.insert(Probe(seen.clone()));
+    assert!(probe > c);
+    restored.connect(sub, 0, probe, 0).unwrap();
+    restored.compile_sinks(&[probe]).unwrap().run(&ProcessContext::default());
+    assert_eq!(*seen.lock().unwrap(), -1.5);
+
+    // Nodes of unregistered types can't be saved.
+    assert!(GraphDocument::from_graph(&restored, &registry).is_err());
+
+    // Missing parameters take their defaults, wrong kinds are refused.
+    let text = r#"
+        [[nodes]]
+        id = 0
+        type = "cables-core/sine-oscillator"
+
+        [[nodes]]
+        id = 1
+        type = "cables-core/gain"
+        parameters = { amount = 2 }
+    "#;
+    let graph = toml::from_str::<GraphDocument>(text).unwrap().to_graph(&registry).unwrap();
+    assert_eq!(graph.get::<SineOscillator>(NodeKey(0)).unwrap().frequency, 440.0);
+    assert_eq!(graph.get::<Gain>(NodeKey(1)).unwrap().amount.get(), 2.0);
+    let text = r#"
+        [[nodes]]
+        id = 0
+        type = "cables-core/gain"
+        parameters = { amount = "loud" }
+    "#;
+    assert!(toml::from_str::<GraphDocument>(text).unwrap().to_graph(&registry).is_err());
+
+    // The largest key would leave none for the nodes inserted after loading.
+    let mut document = GraphDocument::from_graph(&graph, &registry).unwrap();
+    document.nodes[0].id = usize::MAX;
+    assert!(matches!(
+        document.to_graph(&registry),
+        Err(crate::document::DocumentError::NodeIdOutOfRange(usize::MAX))
+    ));
+}
+
+#[test]
+fn parameters_change_while_running() {
+    let mut graph = Graph::new();
+
+    let source = graph.insert(NumSource::new(1.0));
+    let seen = Arc::new(Mutex::new(0.0));
+    let probe = graph.insert(Probe(seen.clone()));
+    graph.connect(source, 0, probe, 0).unwrap();
+
+    let knob = graph.get::<NumSource>(source).unwrap().value.clone();
+    let mut pipeline = graph.compile_sinks(&[probe]).unwrap();
+    let context = ProcessContext::default();
+
+    pipeline.run(&context);
+    assert_eq!(*seen.lock().unwrap(), 1.0);
+
+    // No recompilation needed, from this thread or another.
+    std::thread::spawn(move || knob.set(3.0)).join().unwrap();
+    pipeline.run(&context);
+    assert_eq!(*seen.lock().unwrap(), 3.0);
+
+    // A smoothed parameter glides towards its target, one block at a time.
+    let mut smoothed = param::Param::smoothed(0.0, 0.01);
+    let knob = smoothed.clone();
+    let context = ProcessContext::new(1000, 10);
+    knob.set(1.0);
+    let first = smoothed.next_block(&context);
+    let second = smoothed.next_block(&context);
+    assert!(0.0 < first && first < second && second < 1.0, "{first}, {second}");
+    assert!((first - (1.0 - (-1.0f64).exp())).abs() < 1e-12);
+    for _ in 0..100 {
+        smoothed.next_block(&context);
+    }
+    assert!((smoothed.next_block(&context) - 1.0).abs() < 1e-9);
+
+    // Without a sample rate, there's no telling how long a block is, so it snaps.
+    knob.set(2.0);
+    assert_eq!(smoothed.next_block(&ProcessContext::new(0, 10)), 2.0);
+}
+
+#[test]
+fn incremental_recompile() {
+    /// Counts how many times it ran since it was bound.
+    struct Counter;
+
+    impl Node for Counter {
+        fn bind_parameters<'pip>(&self, parameters: &mut dyn Iterator<Item=NonNull<u8>>) -> Box<dyn FnMut(&ProcessContext) + Send + 'pip> {
+            let out = as_output::<f64>(parameters.next().unwrap());
+            let mut count = 0.0;
+            Box::new(move |_| {
+                count += 1.0;
+                *out = count;
+            })
+        }
+
+        fn input_socket(&self, _socket_index: usize) -> Option<SocketData> {
+            None
+        }
+
+        fn output_socket(&self, socket_index: usize) -> Option<SocketData> {
+            match socket_index {
+                0 => Some(SocketData::new::<f64>()),
+                _ => None,
+            }
+        }
+    }
+
+    /// Passes its input through, which falls back to the value it holds.
+    struct Fallback(f64);
+
+    impl Node for Fallback {
+        fn bind_parameters<'pip>(&self, parameters: &mut dyn Iterator<Item=NonNull<u8>>) -> Box<dyn FnMut(&ProcessContext) + Send + 'pip> {
+            let in1 = as_input::<f64>(parameters.next().unwrap());
+            let out = as_output::<f64>(parameters.next().unwrap());
+            Box::new(move |_| *out = *in1)
+        }
+
+        fn input_socket(&self, socket_index: usize) -> Option<SocketData> {
+            match socket_index {
+                0 => Some(SocketData::new::<f64>().with_default(self.0)),
+                _ => None,
+            }
+        }
+
+        fn output_socket(&self, socket_index: usize) -> Option<SocketData> {
+            match socket_index {
+                0 => Some(SocketData::new::<f64>()),
+                _ => None,
+            }
+        }
+    }
+
+    let mut graph = Graph::new();
+    let context = ProcessContext::default();
+
+    let counter = graph.insert(Counter);
+    let counted = Arc::new(Mutex::new(0.0));
+    let probe = graph.insert(Probe(counted.clone()));
+    graph.connect(counter, 0, probe, 0).unwrap();
+
+    let mut pipeline = graph.compile_all().unwrap();
+    for _ in 0..3 {
+        pipeline.run(&context);
+    }
+    assert_eq!(*counted.lock().unwrap(), 3.0);
+
+    // A new sink joins the mix, the counter keeps counting.
+    let source = graph.insert(NumSource::new(7.0));
+    let other = Arc::new(Mutex::new(0.0));
+    let other_probe = graph.insert(Probe(other.clone()));
+    graph.connect(source, 0, other_probe, 0).unwrap();
+    pipeline.recompile(&graph).unwrap();
+    pipeline.run(&context);
+    assert_eq!(*counted.lock().unwrap(), 4.0);
+    assert_eq!(*other.lock().unwrap(), 7.0);
+
+    // Rewiring only rebinds what reads from a different place.
+    let editor = pipeline.editor();
+    let double = graph.insert(Double);
+    graph.disconnect(probe, 0).unwrap();
+    graph.connect(counter, 0, double, 0).unwrap();
+    graph.connect(double, 0, probe, 0).unwrap();
+    let patch = editor.patch(&graph).unwrap();
+    assert_eq!(patch.rebound_nodes(), 2);
+
+    // Patches can be made on one thread and applied on another.
+    let patch = std::thread::spawn(move || patch).join().unwrap();
+    let stale = pipeline.editor().patch(&graph).unwrap();
+    drop(pipeline.apply(patch).ok().unwrap());
+    assert!(pipeline.apply(stale).is_err());
+    pipeline.run(&context);
+    assert_eq!(*counted.lock().unwrap(), 10.0);
+
+    // A patch that's never applied leaves its editor as it was,
+    // and the next patch builds on the one that was.
+    let editor = pipeline.editor();
+    drop(editor.patch(&graph).unwrap());
+    let retired = pipeline.apply(editor.patch(&graph).unwrap()).ok().unwrap();
+    assert!(pipeline.apply(retired.editor().patch(&graph).unwrap()).is_ok());
+    pipeline.run(&context);
+    assert_eq!(*counted.lock().unwrap(), 12.0);
+
+    // Editing a node binds it again, resetting its state.
+    graph.get_mut::<Counter>(counter).unwrap();
+    pipeline.recompile(&graph).unwrap();
+    pipeline.run(&context);
+    assert_eq!(*counted.lock().unwrap(), 2.0);
+
+    // Editing a node gives it new defaults, even if their types stay the same.
+    let fallback = graph.insert(Fallback(1.0));
+    let fallen = Arc::new(Mutex::new(0.0));
+    let fallback_probe = graph.insert(Probe(fallen.clone()));
+    graph.connect(fallback, 0, fallback_probe, 0).unwrap();
+    pipeline.recompile(&graph).unwrap();
+    pipeline.run(&context);
+    assert_eq!(*fallen.lock().unwrap(), 1.0);
+    graph.get_mut::<Fallback>(fallback).unwrap().0 = 2.0;
+    pipeline.recompile(&graph).unwrap();
+    pipeline.run(&context);
+    assert_eq!(*fallen.lock().unwrap(), 2.0);
+    assert_eq!(*counted.lock().unwrap(), 6.0);
+
+    // Removing a sink's input makes the patch fail, and the pipeline stays as it was.
+    graph.remove(double);
+    assert_eq!(
+        pipeline.recompile(&graph).err(),
+        Some(CompileError::UnconnectedInput { node: probe, socket: 0 })
+    );
+    pipeline.run(&context);
+    assert_eq!(*counted.lock().unwrap(), 8.0);
+}
+
+#[test]
+fn registry_descriptors() {
+    use crate::registry::{NodeRegistry, ParameterValue};
+
+    let registry = NodeRegistry::with_builtin_nodes();
+
+    let gain = registry.descriptor("cables-core/gain").unwrap();
+    assert_eq!(gain.type_id, Some("cables-core/gain"));
+    assert_eq!(gain.name, "Gain");
+    assert_eq!(gain.category, "Mixing");
+    assert_eq!(gain.inputs[0].name, Some("signal"));
+    assert_eq!(gain.outputs[0].name, Some("out"));
+    assert_eq!(gain.parameters[0].name, "amount");
+    assert_eq!(gain.parameters[0].default, ParameterValue::Float(1.0));
+
+    let descriptors = registry.descriptors();
+    assert_eq!(descriptors.len(), registry.type_ids().count());
+    let categories: Vec<_> = descriptors.iter().map(|descriptor| descriptor.category).collect();
+    assert!(categories.is_sorted());
+}
+
+#[test]
+fn registry_unregister() {
+    use crate::registry::{NodeRegistry, Parameters};
+
+    let mut registry = NodeRegistry::with_builtin_nodes();
+    let gain = registry.create("cables-core/gain", &Parameters::new()).unwrap();
+    assert_eq!(registry.type_id_of(gain.as_ref()), Some("cables-core/gain"));
+
+    assert!(registry.unregister("cables-core/gain"));
+    assert!(!registry.unregister("cables-core/gain"));
+    assert!(!registry.contains("cables-core/gain"));
+    assert!(registry.descriptor("cables-core/gain").is_none());
+    assert_eq!(registry.type_id_of(gain.as_ref()), None);
+    assert!(registry.create("cables-core/gain", &Parameters::new()).is_err());
+}
@@ -1,18 +1,29 @@
 use std::hint::black_box;
 use std::ptr::NonNull;
-use crate::graph::{Node, SocketData};
+use crate::audio::AudioBlock;
+use crate::descriptor::NodeDescriptor;
+use crate::graph::{Node, ProcessContext, SocketData};
+use crate::param::Param;
+use crate::registry::{NodeRegistry, NodeType, ParameterError, ParameterSchema, ParameterValue, Parameters};
 
+/// Outputs a number, which can be changed while the pipeline runs.
 pub struct NumSource {
-    pub value: f64,
+    pub value: Param,
+}
+
+impl NumSource {
+    pub fn new(value: f64) -> Self {
+        Self { value: Param::new(value) }
+    }
 }
 
 impl Node for NumSource {
-    fn bind_parameters<'pip>(&self, parameters: &mut dyn Iterator<Item=NonNull<u8>>) -> Box<dyn FnMut() + 'pip> {
-        let value = self.value;
+    fn bind_parameters<'pip>(&self, parameters: &mut dyn Iterator<Item=NonNull<u8>>) -> Box<dyn FnMut(&ProcessContext) + Send + 'pip> {
+        let mut value = self.value.clone();
         let out = crate::as_output::<f64>(parameters.next().unwrap());
 
-        Box::new(move || {
-            *out = value;
+        Box::new(move |context| {
+            *out = value.next_block(context);
         })
     }
 
@@ -24,62 +35,119 @@ impl Node for NumSource {
 
     fn output_socket(&self, socket_index: usize) -> Option<SocketData> {
         match socket_index {
-            0 => Some(SocketData::new::<f64>()),
+            0 => Some(SocketData::new::<f64>().named("out")),
             _ => None,
         }
     }
+
+    fn descriptor(&self) -> NodeDescriptor {
+        NodeDescriptor::of(self)
+            .named("Number")
+            .in_category("Generators")
+            .with_description("Outputs a number that can be changed while the pipeline runs.")
+    }
 }
 
 pub struct Sum;
 
 impl Node for Sum {
-    fn bind_parameters<'pip>(&self, parameters: &mut dyn Iterator<Item=NonNull<u8>>) -> Box<dyn FnMut() + 'pip> {
+    fn bind_parameters<'pip>(&self, parameters: &mut dyn Iterator<Item=NonNull<u8>>) -> Box<dyn FnMut(&ProcessContext) + Send + 'pip> {
+        let in1 = crate::as_input::<f64>(parameters.next().unwrap());
+        let in2 = crate::as_input::<f64>(parameters.next().unwrap());
+        let out = crate::as_output::<f64>(parameters.next().unwrap());
+
+        Box::new(move |_| *out = *in1 + *in2)
+    }
+
+    fn input_socket(&self, socket_index: usize) -> Option<SocketData> {
+        match socket_index {
+            0 => Some(SocketData::new::<f64>().named("a")),
+            1 => Some(SocketData::new::<f64>().named("b")),
+            _ => None,
+        }
+    }
+
+    fn output_socket(&self, socket_index: usize) -> Option<SocketData> {
+        match socket_index {
+            0 => Some(SocketData::new::<f64>().named("out")),
+            _ => None,
+        }
+    }
+
+    fn descriptor(&self) -> NodeDescriptor {
+        NodeDescriptor::of(self)
+            .named("Sum")
+            .in_category("Math")
+            .with_description("Adds two numbers.")
+    }
+}
+
+/// Subtracts the second input from the first.
+/// The second input is optional and defaults to zero.
+pub struct Subtract;
+
+impl Node for Subtract {
+    fn bind_parameters<'pip>(&self, parameters: &mut dyn Iterator<Item=NonNull<u8>>) -> Box<dyn FnMut(&ProcessContext) + Send + 'pip> {
         let in1 = crate::as_input::<f64>(parameters.next().unwrap());
         let in2 = crate::as_input::<f64>(parameters.next().unwrap());
         let out = crate::as_output::<f64>(parameters.next().unwrap());
 
-        Box::new(move || *out = *in1 + *in2)
+        Box::new(move |_| *out = *in1 - *in2)
     }
 
     fn input_socket(&self, socket_index: usize) -> Option<SocketData> {
         match socket_index {
-            0 => Some(SocketData::new::<f64>()),
-            1 => Some(SocketData::new::<f64>()),
+            0 => Some(SocketData::new::<f64>().named("a")),
+            1 => Some(SocketData::new::<f64>().named("b").with_default(0.0)),
             _ => None,
         }
     }
 
     fn output_socket(&self, socket_index: usize) -> Option<SocketData> {
         match socket_index {
-            0 => Some(SocketData::new::<f64>()),
+            0 => Some(SocketData::new::<f64>().named("out")),
             _ => None,
         }
     }
+
+    fn descriptor(&self) -> NodeDescriptor {
+        NodeDescriptor::of(self)
+            .named("Subtract")
+            .in_category("Math")
+            .with_description("Subtracts the second number from the first.")
+    }
 }
 
 pub struct Double;
 
 impl Node for Double {
-    fn bind_parameters<'pip>(&self, parameters: &mut dyn Iterator<Item=NonNull<u8>>) -> Box<dyn FnMut() + 'pip> {
+    fn bind_parameters<'pip>(&self, parameters: &mut dyn Iterator<Item=NonNull<u8>>) -> Box<dyn FnMut(&ProcessContext) + Send + 'pip> {
         let in1 = crate::as_input::<f64>(parameters.next().unwrap());
         let out = crate::as_output::<f64>(parameters.next().unwrap());
 
-        Box::new(move || *out = *in1 * 2.0)
+        Box::new(move |_| *out = *in1 * 2.0)
     }
 
     fn input_socket(&self, socket_index: usize) -> Option<SocketData> {
         match socket_index {
-            0 => Some(SocketData::new::<f64>()),
+            0 => Some(SocketData::new::<f64>().named("in")),
             _ => None,
         }
     }
 
     fn output_socket(&self, socket_index: usize) -> Option<SocketData> {
         match socket_index {
-            0 => Some(SocketData::new::<f64>()),
+            0 => Some(SocketData::new::<f64>().named("out")),
             _ => None,
         }
     }
+
+    fn descriptor(&self) -> NodeDescriptor {
+        NodeDescriptor::of(self)
+            .named("Double")
+            .in_category("Math")
+            .with_description("Multiplies a number by two.")
+    }
 }
 
 // #[derive(Default)]
@@ -100,13 +168,140 @@ impl Node for Double {
 //     }
 // }
 
+/// Generates a sine wave, one block at a time.
+///
+/// The phase comes from the transport position, so the wave stays continuous across blocks.
+pub struct SineOscillator {
+    pub frequency: f64,
+}
+
+impl Node for SineOscillator {
+    fn bind_parameters<'pip>(&self, parameters: &mut dyn Iterator<Item=NonNull<u8>>) -> Box<dyn FnMut(&ProcessContext) + Send + 'pip> {
+        let frequency = self.frequency;
+        let out = crate::as_output::<AudioBlock>(parameters.next().unwrap());
+
+        Box::new(move |context| {
+            let step = frequency * std::f64::consts::TAU / context.sample_rate as f64;
+            for (offset, sample) in out.frames_mut(context).iter_mut().enumerate() {
+                let frame = context.frame + offset as u64;
+                *sample = (frame as f64 * step).sin() as f32;
+            }
+        })
+    }
+
+    fn input_socket(&self, socket_index: usize) -> Option<SocketData> {
+        None
+    }
+
+    fn output_socket(&self, socket_index: usize) -> Option<SocketData> {
+        match socket_index {
+            0 => Some(SocketData::new::<AudioBlock>().named("out")),
+            _ => None,
+        }
+    }
+
+    fn descriptor(&self) -> NodeDescriptor {
+        NodeDescriptor::of(self)
+            .named("Sine Oscillator")
+            .in_category("Generators")
+            .with_description("Outputs a sine wave.")
+    }
+}
+
+/// Multiplies a block of audio by an amount, which can be changed while the pipeline runs.
+pub struct Gain {
+    pub amount: Param,
+}
+
+impl Gain {
+    pub fn new(amount: f32) -> Self {
+        Self { amount: Param::new(amount as f64) }
+    }
+}
+
+impl Node for Gain {
+    fn bind_parameters<'pip>(&self, parameters: &mut dyn Iterator<Item=NonNull<u8>>) -> Box<dyn FnMut(&ProcessContext) + Send + 'pip> {
+        let mut amount = self.amount.clone();
+        let signal = crate::as_input::<AudioBlock>(parameters.next().unwrap());
+        let out = crate::as_output::<AudioBlock>(parameters.next().unwrap());
+
+        Box::new(move |context| {
+            let amount = amount.next_block(context) as f32;
+            for (out, sample) in out.frames_mut(context).iter_mut().zip(signal.frames(context)) {
+                *out = *sample * amount;
+            }
+        })
+    }
+
+    fn input_socket(&self, socket_index: usize) -> Option<SocketData> {
+        match socket_index {
+            0 => Some(SocketData::new::<AudioBlock>().named("signal")),
+            _ => None,
+        }
+    }
+
+    fn output_socket(&self, socket_index: usize) -> Option<SocketData> {
+        match socket_index {
+            0 => Some(SocketData::new::<AudioBlock>().named("out")),
+            _ => None,
+        }
+    }
+
+    fn descriptor(&self) -> NodeDescriptor {
+        NodeDescriptor::of(self)
+            .named("Gain")
+            .in_category("Mixing")
+            .with_description("Changes the volume of a signal.")
+    }
+}
+
+/// Adds two blocks of audio together.
+pub struct Mix;
+
+impl Node for Mix {
+    fn bind_parameters<'pip>(&self, parameters: &mut dyn Iterator<Item=NonNull<u8>>) -> Box<dyn FnMut(&ProcessContext) + Send + 'pip> {
+        let in1 = crate::as_input::<AudioBlock>(parameters.next().unwrap());
+        let in2 = crate::as_input::<AudioBlock>(parameters.next().unwrap());
+        let out = crate::as_output::<AudioBlock>(parameters.next().unwrap());
+
+        Box::new(move |context| {
+            let inputs = in1.frames(context).iter().zip(in2.frames(context));
+            for (out, (a, b)) in out.frames_mut(context).iter_mut().zip(inputs) {
+                *out = *a + *b;
+            }
+        })
+    }
+
+    fn input_socket(&self, socket_index: usize) -> Option<SocketData> {
+        match socket_index {
+            0 => Some(SocketData::new::<AudioBlock>().named("a")),
+            1 => Some(SocketData::new::<AudioBlock>().named("b")),
+            _ => None,
+        }
+    }
+
+    fn output_socket(&self, socket_index: usize) -> Option<SocketData> {
+        match socket_index {
+            0 => Some(SocketData::new::<AudioBlock>().named("out")),
+            _ => None,
+        }
+    }
+
+    fn descriptor(&self) -> NodeDescriptor {
+        NodeDescriptor::of(self)
+            .named("Mix")
+            .in_category("Mixing")
+            .with_description("Adds two signals together.")
+    }
+}
+
 pub struct YellNum;
 
 impl Node for YellNum {
-    fn bind_parameters<'pip>(&self, parameters: &mut dyn Iterator<Item=NonNull<u8>>) -> Box<dyn FnMut() + 'pip> {
+    fn bind_parameters<'pip>(&self, parameters: &mut dyn Iterator<Item=NonNull<u8>>) -> Box<dyn FnMut(&ProcessContext) + Send + 'pip> {
         let in1 = crate::as_input::<f64>(parameters.next().unwrap());
 
-        Box::new(move || {
+        Box::new(move |_| {
             //print!("{};", *in1);
             black_box(*in1);
         })
@@ -114,7 +309,7 @@ impl Node for YellNum {
 
     fn input_socket(&self, socket_index: usize) -> Option<SocketData> {
         match socket_index {
-            0 => Some(SocketData::new::<f64>()),
+            0 => Some(SocketData::new::<f64>().named("in")),
             _ => None,
         }
     }
@@ -124,5 +319,101 @@ impl Node for YellNum {
             _ => None,
         }
     }
+
+    fn descriptor(&self) -> NodeDescriptor {
+        NodeDescriptor::of(self)
+            .named("Yell Number")
+            .in_category("Debug")
+            .with_description("Reads a number and does nothing with it.")
+    }
 }
 
+
+/// Registers every node in this module.
+pub fn register(registry: &mut NodeRegistry) {
+    registry.register::<NumSource>();
+    registry.register::<Sum>();
+    registry.register::<Subtract>();
+    registry.register::<Double>();
+    registry.register::<SineOscillator>();
+    registry.register::<Gain>();
+    registry.register::<Mix>();
+    registry.register::<YellNum>();
+}
+
+impl NodeType for NumSource {
+    const TYPE_ID: &'static str = "cables-core/num-source";
+
+    fn parameter_schema() -> Vec<ParameterSchema> {
+        vec![ParameterSchema::new("value", ParameterValue::Float(0.0))]
+    }
+
+    fn from_parameters(parameters: &Parameters) -> Result<Self, ParameterError> {
+        Ok(NumSource::new(parameters.float("value")?))
+    }
+
+    fn to_parameters(&self) -> Parameters {
+        Parameters::new().with("value", ParameterValue::Float(self.value.get()))
+    }
+}
+
+impl NodeType for SineOscillator {
+    const TYPE_ID: &'static str = "cables-core/sine-oscillator";
+
+    fn parameter_schema() -> Vec<ParameterSchema> {
+        vec![ParameterSchema::new("frequency", ParameterValue::Float(440.0))]
+    }
+
+    fn from_parameters(parameters: &Parameters) -> Result<Self, ParameterError> {
+        Ok(SineOscillator { frequency: parameters.float("frequency")? })
+    }
+
+    fn to_parameters(&self) -> Parameters {
+        Parameters::new().with("frequency", ParameterValue::Float(self.frequency))
+    }
+}
+
+impl NodeType for Gain {
+    const TYPE_ID: &'static str = "cables-core/gain";
+
+    fn parameter_schema() -> Vec<ParameterSchema> {
+        vec![
+            ParameterSchema::new("amount", ParameterValue::Float(1.0)),
+            ParameterSchema::new("smoothing", ParameterValue::Float(0.0)),
+        ]
+    }
+
+    fn from_parameters(parameters: &Parameters) -> Result<Self, ParameterError> {
+        let amount = parameters.float("amount")?;
+        let smoothing = parameters.float("smoothing")?;
+        Ok(Gain { amount: Param::smoothed(amount, smoothing) })
+    }
+
+    fn to_parameters(&self) -> Parameters {
+        Parameters::new()
+            .with("amount", ParameterValue::Float(self.amount.get()))
+            .with("smoothing", ParameterValue::Float(self.amount.smoothing()))
+    }
+}
+
+macro_rules! unit_node_type {
+    ($($node:ident => $id:literal),* $(,)?) => {
+        $(
+            impl NodeType for $node {
+                const TYPE_ID: &'static str = $id;
+
+                fn from_parameters(_: &Parameters) -> Result<Self, ParameterError> {
+                    Ok($node)
+                }
+            }
+        )*
+    };
+}
+
+unit_node_type! {
+    Sum => "cables-core/sum",
+    Subtract => "cables-core/subtract",
+    Double => "cables-core/double",
+    Mix => "cables-core/mix",
+    YellNum => "cables-core/yell-num",
+}
@@ -0,0 +1,128 @@
+//! # Parameters
+//!
+//! A node's fields are copied into its pipeline closure when the graph is compiled, so
+//! editing them afterwards does nothing until the next compile. A [`Param`] is a field
+//! that stays shared instead: every clone of it reads and writes the same value, without
+//! locking, so a UI or automation thread can turn a knob while the pipeline runs.
+//!
+//! ```
+//! use cables_core::param::Param;
+//!
+//! let volume = Param::smoothed(1.0, 0.05);
+//! let knob = volume.clone();
+//! knob.set(0.5);
+//! assert_eq!(volume.get(), 0.5);
+//! ```
+
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::graph::ProcessContext;
+
+/// A number shared between a node and whoever controls it.
+///
+/// Cloning a `Param` gives another handle to the same value.
+/// Nodes read it once per block with [`Param::next_block`], which applies the
+/// smoothing (if any) so sudden changes don't click.
+pub struct Param {
+    shared: Arc<Shared>,
+    /// The value the last block was processed with, only used for smoothing.
+    current: f64,
+}
+
+struct Shared {
+    target: AtomicU64,
+    /// How long, in seconds, the value takes to (mostly) reach a new target.
+    smoothing: f64,
+}
+
+impl Param {
+    /// A parameter that jumps to new values immediately.
+    pub fn new(value: f64) -> Self {
+        Self::smoothed(value, 0.0)
+    }
+
+    /// A parameter that glides towards new values.
+    ///
+    /// `seconds` is the time constant of the glide: after that long the value has
+    /// covered about two thirds of the distance to its target.
+    pub fn smoothed(value: f64, seconds: f64) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                target: AtomicU64::new(value.to_bits()),
+                smoothing: seconds.max(0.0),
+            }),
+            current: value,
+        }
+    }
+
+    /// The last value that was [set](Param::set).
+    pub fn get(&self) -> f64 {
+        f64::from_bits(self.shared.target.load(Ordering::Relaxed))
+    }
+
+    /// Sets a new value. Every handle to this parameter will see it.
+    pub fn set(&self, value: f64) {
+        self.shared.target.store(value.to_bits(), Ordering::Relaxed);
+    }
+
+    /// The smoothing time this parameter was created with, in seconds.
+    pub fn smoothing(&self) -> f64 {
+        self.shared.smoothing
+    }
+
+    /// Returns the value to process the current block with.
+    ///
+    /// Call it once per run. Without smoothing, or without a sample rate to measure
+    /// the block's length by, this is just [`Param::get`].
+    pub fn next_block(&mut self, context: &ProcessContext) -> f64 {
+        let target = self.get();
+        let smoothing = self.shared.smoothing;
+
+        if smoothing == 0.0 || context.sample_rate == 0 {
+            self.current = target;
+        } else {
+            let block_seconds = context.block_len as f64 / context.sample_rate as f64;
+            let step = 1.0 - (-block_seconds / smoothing).exp();
+            self.current += (target - self.current) * step;
+        }
+
+        self.current
+    }
+
+    /// Whether two handles control the same value.
+    pub fn ptr_eq(&self, other: &Param) -> bool {
+        Arc::ptr_eq(&self.shared, &other.shared)
+    }
+}
+
+/// The clone shares the value, but starts its smoothing at the current target.
+impl Clone for Param {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+            current: self.get(),
+        }
+    }
+}
+
+impl Default for Param {
+    fn default() -> Self {
+        Self::new(0.0)
+    }
+}
+
+impl From<f64> for Param {
+    fn from(value: f64) -> Self {
+        Self::new(value)
+    }
+}
+
+impl fmt::Debug for Param {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Param")
+            .field("value", &self.get())
+            .field("smoothing", &self.shared.smoothing)
+            .finish()
+    }
+}
@@ -0,0 +1,357 @@
+//! # Node Registry
+//!
+//! A [`Graph`](crate::graph::Graph) holds its nodes as `Box<dyn Node>`, which can't be
+//! written to disk or recreated from it by themselves. A [`NodeRegistry`] maps stable,
+//! string type ids to constructors and parameter schemas, so nodes can be saved as
+//! "a node of type X with these parameters" and built again later.
+
+use std::any::{Any, TypeId};
+use std::collections::{BTreeMap, HashMap};
+use crate::descriptor::NodeDescriptor;
+use crate::graph::Node;
+
+/// A node type that can be registered in a [`NodeRegistry`].
+pub trait NodeType: Node + Sized {
+    /// The identifier this type is saved as.
+    ///
+    /// It must be unique and should never change once graphs have been saved with it,
+    /// so prefix it with the name of your crate, like `"cables-core/gain"`.
+    const TYPE_ID: &'static str;
+
+    /// Describes the parameters this type is built from.
+    fn parameter_schema() -> Vec<ParameterSchema> {
+        Vec::new()
+    }
+
+    /// Builds a node from its parameters.
+    ///
+    /// Every parameter in the schema is guaranteed to be present and of the declared kind.
+    fn from_parameters(parameters: &Parameters) -> Result<Self, ParameterError>;
+
+    /// Returns the parameters that would build this node back.
+    fn to_parameters(&self) -> Parameters {
+        Parameters::new()
+    }
+}
+
+/// The value of a single node parameter.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(untagged))]
+pub enum ParameterValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl ParameterValue {
+    /// The kind of this value.
+    pub fn kind(&self) -> ParameterKind {
+        match self {
+            ParameterValue::Bool(_) => ParameterKind::Bool,
+            ParameterValue::Int(_) => ParameterKind::Int,
+            ParameterValue::Float(_) => ParameterKind::Float,
+            ParameterValue::Text(_) => ParameterKind::Text,
+        }
+    }
+}
+
+/// The kind of value a parameter holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParameterKind {
+    Bool,
+    Int,
+    /// A float parameter also accepts integers, since `440` is a fine frequency.
+    Float,
+    Text,
+}
+
+impl ParameterKind {
+    fn accepts(self, value: &ParameterValue) -> bool {
+        matches!(
+            (self, value),
+            (ParameterKind::Bool, ParameterValue::Bool(_))
+                | (ParameterKind::Int, ParameterValue::Int(_))
+                | (ParameterKind::Float, ParameterValue::Float(_) | ParameterValue::Int(_))
+                | (ParameterKind::Text, ParameterValue::Text(_))
+        )
+    }
+}
+
+/// Describes one parameter of a [`NodeType`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParameterSchema {
+    pub name: &'static str,
+    pub kind: ParameterKind,
+    /// The value used when a saved node doesn't mention this parameter.
+    pub default: ParameterValue,
+}
+
+impl ParameterSchema {
+    pub fn new(name: &'static str, default: ParameterValue) -> Self {
+        Self {
+            name,
+            kind: default.kind(),
+            default,
+        }
+    }
+}
+
+/// The named parameters a node is built from.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Parameters(BTreeMap<String, ParameterValue>);
+
+impl Parameters {
+    pub fn new() -> Self {
+        Self(BTreeMap::new())
+    }
+
+    /// Sets a parameter, returning `self` so calls can be chained.
+    pub fn with(mut self, name: &str, value: ParameterValue) -> Self {
+        self.set(name, value);
+        self
+    }
+
+    pub fn set(&mut self, name: &str, value: ParameterValue) {
+        self.0.insert(name.to_string(), value);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ParameterValue> {
+        self.0.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ParameterValue)> {
+        self.0.iter().map(|(name, value)| (name.as_str(), value))
+    }
+
+    pub fn bool(&self, name: &str) -> Result<bool, ParameterError> {
+        match self.get(name) {
+            Some(ParameterValue::Bool(value)) => Ok(*value),
+            other => Err(ParameterError::expected(name, ParameterKind::Bool, other)),
+        }
+    }
+
+    pub fn int(&self, name: &str) -> Result<i64, ParameterError> {
+        match self.get(name) {
+            Some(ParameterValue::Int(value)) => Ok(*value),
+            other => Err(ParameterError::expected(name, ParameterKind::Int, other)),
+        }
+    }
+
+    pub fn float(&self, name: &str) -> Result<f64, ParameterError> {
+        match self.get(name) {
+            Some(ParameterValue::Float(value)) => Ok(*value),
+            Some(ParameterValue::Int(value)) => Ok(*value as f64),
+            other => Err(ParameterError::expected(name, ParameterKind::Float, other)),
+        }
+    }
+
+    pub fn text(&self, name: &str) -> Result<&str, ParameterError> {
+        match self.get(name) {
+            Some(ParameterValue::Text(value)) => Ok(value),
+            other => Err(ParameterError::expected(name, ParameterKind::Text, other)),
+        }
+    }
+}
+
+/// An error that happened while reading a node's parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParameterError {
+    /// The parameter is required but wasn't given.
+    Missing(String),
+    /// The parameter was given with the wrong kind of value.
+    WrongKind {
+        name: String,
+        expected: ParameterKind,
+        found: ParameterKind,
+    },
+    /// The parameter has the right kind, but the node can't work with its value.
+    Invalid { name: String, reason: String },
+}
+
+impl ParameterError {
+    fn expected(name: &str, expected: ParameterKind, found: Option<&ParameterValue>) -> Self {
+        match found {
+            None => ParameterError::Missing(name.to_string()),
+            Some(value) => ParameterError::WrongKind {
+                name: name.to_string(),
+                expected,
+                found: value.kind(),
+            },
+        }
+    }
+}
+
+/// An error that happened while using a [`NodeRegistry`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegistryError {
+    /// No node type was registered under this id.
+    UnknownType(String),
+    /// The node's concrete type was never registered, so it can't be saved.
+    UnregisteredNode(&'static str),
+    /// The parameters couldn't build a node of this type.
+    Parameter {
+        type_id: String,
+        error: ParameterError,
+    },
+}
+
+/// Everything the registry knows about one node type.
+#[derive(Debug)]
+struct RegisteredType {
+    schema: Vec<ParameterSchema>,
+    descriptor: NodeDescriptor,
+    construct: fn(&Parameters) -> Result<Box<dyn Node>, ParameterError>,
+    save: fn(&dyn Node) -> Parameters,
+}
+
+/// Maps node type ids to the functions that build and save them.
+#[derive(Debug, Default)]
+pub struct NodeRegistry {
+    types: BTreeMap<&'static str, RegisteredType>,
+    type_ids: HashMap<TypeId, &'static str>,
+}
+
+impl NodeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A registry that already knows all of [`crate::nodes`].
+    pub fn with_builtin_nodes() -> Self {
+        let mut registry = Self::new();
+        crate::nodes::register(&mut registry);
+        registry
+    }
+
+    /// Registers a node type under [`NodeType::TYPE_ID`].
+    ///
+    /// Registering a type twice, or two types under the same id, keeps the last one.
+    pub fn register<N: NodeType>(&mut self) {
+        fn construct<N: NodeType>(parameters: &Parameters) -> Result<Box<dyn Node>, ParameterError> {
+            Ok(Box::new(N::from_parameters(parameters)?))
+        }
+        fn save<N: NodeType>(node: &dyn Node) -> Parameters {
+            let node: &dyn Any = node;
+            node.downcast_ref::<N>()
+                .expect("node was saved with another type's function")
+                .to_parameters()
+        }
+
+        let schema = N::parameter_schema();
+        let mut defaults = Parameters::new();
+        for parameter in schema.iter() {
+            defaults.set(parameter.name, parameter.default.clone());
+        }
+        // Sockets may depend on the parameters, so describe a node built from the defaults.
+        let mut descriptor = match N::from_parameters(&defaults) {
+            Ok(node) => node.descriptor(),
+            Err(_) => NodeDescriptor {
+                type_id: None,
+                name: N::TYPE_ID,
+                category: crate::descriptor::UNCATEGORIZED,
+                description: "",
+                inputs: Vec::new(),
+                outputs: Vec::new(),
+                parameters: Vec::new(),
+            },
+        };
+        descriptor.type_id = Some(N::TYPE_ID);
+        descriptor.parameters = schema.clone();
+
+        self.types.insert(
+            N::TYPE_ID,
+            RegisteredType {
+                descriptor,
+                schema,
+                construct: construct::<N>,
+                save: save::<N>,
+            },
+        );
+        self.type_ids.insert(TypeId::of::<N>(), N::TYPE_ID);
+    }
+
+    /// Forgets a registered type, so it can't be created or saved anymore.
+    ///
+    /// Returns whether the type was registered.
+    pub fn unregister(&mut self, type_id: &str) -> bool {
+        self.type_ids.retain(|_, id| *id != type_id);
+        self.types.remove(type_id).is_some()
+    }
+
+    /// Iterates over the ids of all registered types, in alphabetical order.
+    pub fn type_ids(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.types.keys().copied()
+    }
+
+    pub fn contains(&self, type_id: &str) -> bool {
+        self.types.contains_key(type_id)
+    }
+
+    /// Returns the parameters a registered type is built from.
+    pub fn parameter_schema(&self, type_id: &str) -> Option<&[ParameterSchema]> {
+        self.types.get(type_id).map(|registered| registered.schema.as_slice())
+    }
+
+    /// Describes a registered type.
+    pub fn descriptor(&self, type_id: &str) -> Option<&NodeDescriptor> {
+        self.types.get(type_id).map(|registered| &registered.descriptor)
+    }
+
+    /// Describes every registered type, sorted by category and then by name,
+    /// ready to be shown in an "add node" menu.
+    pub fn descriptors(&self) -> Vec<&NodeDescriptor> {
+        let mut descriptors: Vec<&NodeDescriptor> =
+            self.types.values().map(|registered| &registered.descriptor).collect();
+        descriptors.sort_by_key(|descriptor| (descriptor.category, descriptor.name));
+        descriptors
+    }
+
+    /// Builds a node of a registered type.
+    ///
+    /// Parameters missing from `parameters` take the default from the type's schema.
+    pub fn create(&self, type_id: &str, parameters: &Parameters) -> Result<Box<dyn Node>, RegistryError> {
+        let registered = self
+            .types
+            .get(type_id)
+            .ok_or_else(|| RegistryError::UnknownType(type_id.to_string()))?;
+        let parameter_error = |error| RegistryError::Parameter {
+            type_id: type_id.to_string(),
+            error,
+        };
+
+        let mut complete = parameters.clone();
+        for schema in registered.schema.iter() {
+            match parameters.get(schema.name) {
+                None => complete.set(schema.name, schema.default.clone()),
+                Some(value) if !schema.kind.accepts(value) => {
+                    return Err(parameter_error(ParameterError::WrongKind {
+                        name: schema.name.to_string(),
+                        expected: schema.kind,
+                        found: value.kind(),
+                    }));
+                }
+                Some(_) => {}
+            }
+        }
+
+        (registered.construct)(&complete).map_err(parameter_error)
+    }
+
+    /// Returns the type id a node was registered under, if its type was registered.
+    pub fn type_id_of(&self, node: &dyn Node) -> Option<&'static str> {
+        let node: &dyn Any = node;
+        self.type_ids.get(&node.type_id()).copied()
+    }
+
+    /// Returns the type id and parameters that would build this node back.
+    pub fn save(&self, node: &dyn Node) -> Result<(&'static str, Parameters), RegistryError> {
+        let type_id = self
+            .type_id_of(node)
+            .ok_or(RegistryError::UnregisteredNode(std::any::type_name_of_val(node)))?;
+        Ok((type_id, (self.types[type_id].save)(node)))
+    }
+}
@@ -1,31 +1,46 @@
 use proc_macro::TokenStream;
 use proc_macro2::Ident;
-use quote::{format_ident, quote};
+use quote::{format_ident, quote, quote_spanned};
+use syn::spanned::Spanned;
 use syn::{
-    parse_macro_input, Error, FnArg, ImplItem, Index, ItemImpl, LitInt, LitStr, PatType, Type,
-    TypeReference,
+    parse_macro_input, Attribute, Error, Expr, ExprRange, FnArg, GenericArgument, ImplItem,
+    ImplItemFn, Index, ItemImpl, LitInt, LitStr, Pat, PatType, Path, PathArguments, RangeLimits,
+    ReturnType, Type, TypePath, TypeReference,
 };
 
 #[proc_macro_attribute]
 pub fn node_impl(attribute: TokenStream, input: TokenStream) -> TokenStream {
     let impl_block = parse_macro_input!(input as ItemImpl);
 
-    let Some((_, trait_, _)) = impl_block.trait_.as_ref() else {
-        return Error::new_spanned(
-            &impl_block,
-            "this is supposed to be an implementation of `Node`",
-        )
-        .to_compile_error()
-        .into();
-    };
-
     enum FieldRef {
         Indexed(Index),
         Named(Ident),
     }
     let mut self_fields = Vec::new();
+    let mut states = Vec::new();
+    let mut name: Option<LitStr> = None;
+    let mut category: Option<LitStr> = None;
+    let mut description: Option<LitStr> = None;
     let args_parser = syn::meta::parser(|meta| {
-        if meta.path.is_ident("fields") {
+        if meta.path.is_ident("name") {
+            name = Some(meta.value()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("category") {
+            category = Some(meta.value()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("description") {
+            description = Some(meta.value()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("state") {
+            meta.parse_nested_meta(|meta| {
+                let Some(ident) = meta.path.get_ident() else {
+                    return Err(meta.error("expected `name = initial value`"));
+                };
+                let initial: Expr = meta.value()?.parse()?;
+                states.push((ident.clone(), initial));
+                Ok(())
+            })
+        } else if meta.path.is_ident("fields") {
             meta.parse_nested_meta(|meta| {
                 if let Some(ident) = meta.path.get_ident() {
                     if let Ok(v) = meta.value() {
@@ -48,89 +63,167 @@ pub fn node_impl(attribute: TokenStream, input: TokenStream) -> TokenStream {
                 Ok(())
             })
         } else {
-            Err(meta.error("unsupported property"))
+            Err(meta.error(
+                "unsupported property, expected `fields(...)`, `state(...)`, `name`, `category` or `description`",
+            ))
         }
     });
 
     parse_macro_input!(attribute with args_parser);
 
-    let func = impl_block.items.iter().find_map(|item| match item {
-        ImplItem::Fn(f) if f.sig.ident == "process" => Some(f),
-        _ => None,
-    });
-    let Some(func) = func else {
-        return Error::new_spanned(&impl_block, "expected `fn process(...inputs, ...outputs)`")
-            .to_compile_error()
-            .into();
+    let trait_ = match check_trait(&impl_block) {
+        Ok(trait_) => trait_,
+        Err(error) => return error.to_compile_error().into(),
+    };
+    // The path is only checked by name above, so make sure it really is the `Node` trait.
+    // This also keeps the user's import of `Node` from looking unused when there are errors.
+    let node_check = quote_spanned! { trait_.span() =>
+        const _: () = {
+            #[allow(dead_code)]
+            fn is_node(node: &dyn ::cables_core::graph::Node) -> &dyn #trait_ {
+                node
+            }
+        };
+    };
+    let func = match find_process(&impl_block) {
+        Ok(func) => func,
+        Err(error) => {
+            let error = error.to_compile_error();
+            return quote! { #error #node_check }.into();
+        }
     };
 
     let mut inputs = Vec::new();
     let mut outputs = Vec::new();
+    let mut context = None;
+    // Every bad argument is reported at once, each at its own place.
+    let mut errors: Option<Error> = None;
+    let mut report = |error: Error| match &mut errors {
+        Some(errors) => errors.combine(error),
+        None => errors = Some(error),
+    };
 
     for argument in &func.sig.inputs {
-        match argument {
-            FnArg::Typed(PatType { pat, ty, .. }) => match &**ty {
-                Type::Reference(TypeReference {
-                    mutability, elem, ..
-                }) => {
-                    if mutability.is_some() {
-                        outputs.push((pat.clone(), elem.clone()));
-                    } else {
-                        inputs.push((pat.clone(), elem.clone()));
-                    }
+        let PatType { pat, ty, attrs, .. } = match argument {
+            FnArg::Typed(argument) => argument,
+            FnArg::Receiver(receiver) => {
+                report(Error::new_spanned(
+                    receiver,
+                    "`process` can't take `self`, its body runs long after the node was borrowed\n\
+                     help: copy the fields you need into the body with `#[node_impl(fields(name = field))]`",
+                ));
+                continue;
+            }
+        };
+        let metadata = match SocketMetadata::parse(pat, attrs) {
+            Ok(metadata) => metadata,
+            Err(error) => {
+                report(error);
+                continue;
+            }
+        };
+
+        match classify_argument(ty) {
+            Ok(Argument::Context) => context = Some(pat.clone()),
+            Ok(Argument::Input(elem)) => {
+                inputs.push((pat.clone(), elem.clone(), InputKind::Required, metadata));
+            }
+            Ok(Argument::OptionalInput(elem)) => {
+                if let Some(default) = &metadata.default {
+                    report(Error::new_spanned(
+                        default,
+                        "an optional input can't have a default value\n\
+                         help: take `&T` to read the default when unconnected, or keep `Option<&T>` and drop the default",
+                    ));
+                    continue;
                 }
-                &_ => {
-                    return Error::new_spanned(
-                        &impl_block,
-                        "arguments must be `#[field]` (a field of `self`), `&T` (an input) or `&mut T` (an output)",
-                    )
-                    .to_compile_error()
-                    .into();
+                inputs.push((pat.clone(), elem.clone(), InputKind::Optional, metadata));
+            }
+            Ok(Argument::Output(elem)) => {
+                if let Some(default) = &metadata.default {
+                    report(Error::new_spanned(
+                        default,
+                        "only inputs can have a default value, outputs start out as their type's default",
+                    ));
+                    continue;
                 }
-            },
-            FnArg::Receiver(_) => {
-                return Error::new_spanned(
-                    &impl_block,
-                    "can not take self as argument — use `#[field]`",
-                )
-                .to_compile_error()
-                .into();
+                outputs.push((pat.clone(), elem.clone(), metadata));
             }
+            Err(error) => report(error),
         }
     }
 
-    let input_binds = inputs.iter().map(|(pat, ty)| {
-        quote! { let #pat = ::cables_core::as_input::<'pip, #ty>(parameters.next().unwrap()); }
+    if let Some(errors) = errors {
+        let errors = errors.to_compile_error();
+        return quote! { #errors #node_check }.into();
+    }
+
+    let input_binds = inputs.iter().map(|(pat, ty, kind, _)| match kind {
+        InputKind::Required => quote! {
+            let #pat = ::cables_core::as_input::<'pip, #ty>(parameters.next().unwrap());
+        },
+        InputKind::Optional => quote! {
+            let #pat = ::cables_core::as_optional_input::<'pip, #ty>(parameters.next().unwrap());
+        },
     });
-    let output_binds = outputs.iter().map(|(pat, ty)| {
+    let output_binds = outputs.iter().map(|(pat, ty, _)| {
         quote! { let #pat = ::cables_core::as_output::<'pip, #ty>(parameters.next().unwrap()); }
     });
+    // Fields are bound mutably so the body can keep state in them,
+    // like the smoothing of a `cables_core::param::Param`.
     let field_binds = self_fields.iter().map(|(local, field)| match field {
-        FieldRef::Indexed(idx) => quote! { let #local = Clone::clone(&self.#idx); },
-        FieldRef::Named(field) => quote! { let #local = Clone::clone(&self.#field); },
+        FieldRef::Indexed(idx) => quote! { #[allow(unused_mut)] let mut #local = Clone::clone(&self.#idx); },
+        FieldRef::Named(field) => quote! { #[allow(unused_mut)] let mut #local = Clone::clone(&self.#field); },
     });
 
     let body = &func.block;
+    let context_bind = context.map(|pat| quote! { let #pat = __context; });
+    // State lives in the closure and starts over whenever the pipeline is reset.
+    let state_binds = states.iter().map(|(name, initial)| quote! { let mut #name = #initial; });
+    let state_resets = states.iter().map(|(name, initial)| quote! { #name = #initial; });
+    let state_reset = (!states.is_empty()).then(|| {
+        quote! {
+            if __context.reset {
+                #(#state_resets)*
+            }
+        }
+    });
 
     let fn_bind_parameters = quote! {
         fn bind_parameters<'pip>(
             &self,
             parameters: &mut dyn Iterator<Item = ::std::ptr::NonNull<u8>>
-        ) -> Box<dyn FnMut() + 'pip> {
+        ) -> Box<dyn FnMut(&::cables_core::graph::ProcessContext) + Send + 'pip> {
             #(#field_binds)*
             #(#input_binds)*
             #(#output_binds)*
+            #(#state_binds)*
             Box::new(
-                move || #body
+                move |__context: &::cables_core::graph::ProcessContext| {
+                    #state_reset
+                    #context_bind
+                    #body
+                }
             )
         }
     };
 
-    let input_socket_match_arms = inputs.iter().enumerate().map(|(i, (_, ty))| {
-        quote! { #i => Some(::cables_core::graph::SocketData::new::<#ty>()), }
+    let input_socket_match_arms = inputs.iter().enumerate().map(|(i, (_, ty, kind, metadata))| {
+        let default = metadata.default.as_ref().map(|default| quote! { .with_default::<#ty>(#default) });
+        let optional = matches!(kind, InputKind::Optional).then(|| quote! { .optional() });
+        let metadata = metadata.builder_calls();
+        quote! { #i => Some(::cables_core::graph::SocketData::input::<#ty>() #metadata #default #optional), }
     });
-    let output_socket_match_arms = outputs.iter().enumerate().map(|(i, (_, ty))| {
-        quote! { #i => Some(::cables_core::graph::SocketData::new::<#ty>()), }
+    // Outputs whose type is `Default` start out as the default value, and other types
+    // (like references) start uninitialized, for the body to write. See `cables_core::graph::probe`.
+    let output_socket_match_arms = outputs.iter().enumerate().map(|(i, (_, ty, metadata))| {
+        let metadata = metadata.builder_calls();
+        quote! {
+            #i => Some({
+                use ::cables_core::graph::probe::{ViaDefault as _, ViaUninit as _};
+                (&::cables_core::graph::probe::SocketProbe::<#ty>::new()).socket_data() #metadata
+            }),
+        }
     });
 
     let fn_input_socket = quote! {
@@ -151,16 +244,294 @@ pub fn node_impl(attribute: TokenStream, input: TokenStream) -> TokenStream {
         }
     };
 
+    let fn_descriptor = (name.is_some() || category.is_some() || description.is_some()).then(|| {
+        let name = name.map(|name| quote! { .named(#name) });
+        let category = category.map(|category| quote! { .in_category(#category) });
+        let description = description.map(|description| quote! { .with_description(#description) });
+        quote! {
+            fn descriptor(&self) -> ::cables_core::descriptor::NodeDescriptor {
+                ::cables_core::descriptor::NodeDescriptor::of(self) #name #category #description
+            }
+        }
+    });
+
+    let fn_reset = find_reset(&impl_block);
+
     let (impl_generics, _, where_clause) = impl_block.generics.split_for_impl();
     let self_ty = &impl_block.self_ty;
 
     let final_impl = quote! {
-        impl #impl_generics #trait_ for #self_ty #where_clause {
+        impl #impl_generics ::cables_core::graph::Node for #self_ty #where_clause {
             #fn_bind_parameters
             #fn_input_socket
             #fn_output_socket
+            #fn_descriptor
+            #fn_reset
         }
+
+        #node_check
     };
 
     final_impl.into()
 }
+
+/// How an input socket behaves when it isn't connected.
+enum InputKind {
+    /// `&T`: the graph won't compile unless it's connected or has a default.
+    Required,
+    /// `Option<&T>`: reads `None`.
+    Optional,
+}
+
+/// What a `process` argument turns into.
+enum Argument<'a> {
+    Context,
+    Input(&'a Type),
+    OptionalInput(&'a Type),
+    Output(&'a Type),
+}
+
+/// Makes sure the macro is applied to `impl Node for ...`, returning the trait's path.
+fn check_trait(impl_block: &ItemImpl) -> syn::Result<&Path> {
+    let Some((bang, trait_, _)) = impl_block.trait_.as_ref() else {
+        return Err(Error::new_spanned(
+            &impl_block.self_ty,
+            "`#[node_impl]` goes on an implementation of `Node`\n\
+             help: write `impl Node for ...`, with `use cables_core::graph::Node;`",
+        ));
+    };
+    if let Some(bang) = bang {
+        return Err(Error::new_spanned(bang, "`#[node_impl]` can't implement a negative trait"));
+    }
+
+    let last = trait_.segments.last().unwrap();
+    if last.ident != "Node" {
+        return Err(Error::new_spanned(
+            trait_,
+            format!(
+                "`#[node_impl]` implements `cables_core::graph::Node`, not `{}`",
+                quote!(#trait_).to_string().replace(' ', "")
+            ),
+        ));
+    }
+    if !last.arguments.is_none() {
+        return Err(Error::new_spanned(&last.arguments, "`Node` takes no generic arguments"));
+    }
+
+    Ok(trait_)
+}
+
+/// Finds the `process` function and checks that it can be turned into a node.
+fn find_process(impl_block: &ItemImpl) -> syn::Result<&ImplItemFn> {
+    let mut process = None;
+    for item in impl_block.items.iter() {
+        match item {
+            ImplItem::Fn(func) if func.sig.ident == "process" => process = Some(func),
+            // Passed through as is, see `find_reset`.
+            ImplItem::Fn(func) if func.sig.ident == "reset" => {}
+            ImplItem::Fn(func) => {
+                return Err(Error::new_spanned(
+                    &func.sig.ident,
+                    "`#[node_impl]` writes every method of `Node` by itself, only `fn process` and `fn reset` are expected",
+                ));
+            }
+            other => {
+                return Err(Error::new_spanned(other, "only `fn process` and `fn reset` are expected here"));
+            }
+        }
+    }
+
+    let Some(func) = process else {
+        return Err(Error::new_spanned(
+            &impl_block.self_ty,
+            "missing `fn process(...)`\n\
+             help: declare inputs as `&T`, outputs as `&mut T` and the context as `&ProcessContext`",
+        ));
+    };
+
+    let sig = &func.sig;
+    if !sig.generics.params.is_empty() || sig.generics.where_clause.is_some() {
+        return Err(Error::new_spanned(
+            &sig.generics,
+            "`process` can't be generic, its sockets must have concrete types\n\
+             help: put the generic parameters on the `impl` instead",
+        ));
+    }
+    if let ReturnType::Type(_, ty) = &sig.output {
+        return Err(Error::new_spanned(
+            ty,
+            "`process` can't return anything\n\
+             help: write results to an output argument, like `out: &mut T`",
+        ));
+    }
+    if let Some(asyncness) = &sig.asyncness {
+        return Err(Error::new_spanned(asyncness, "`process` can't be `async`, it runs on the audio thread"));
+    }
+    if let Some(variadic) = &sig.variadic {
+        return Err(Error::new_spanned(variadic, "`process` can't be variadic"));
+    }
+
+    Ok(func)
+}
+
+/// Finds the node's own `fn reset(&mut self)`, if it has one, which becomes `Node::reset` as written.
+///
+/// State declared with `state(...)` lives in the bound function instead, and resets with the pipeline.
+fn find_reset(impl_block: &ItemImpl) -> Option<&ImplItemFn> {
+    impl_block.items.iter().find_map(|item| match item {
+        ImplItem::Fn(func) if func.sig.ident == "reset" => Some(func),
+        _ => None,
+    })
+}
+
+/// Figures out what socket (if any) an argument of `process` describes.
+fn classify_argument(ty: &Type) -> syn::Result<Argument<'_>> {
+    const EXPECTED: &str = "help: arguments must be `&T` (an input), `Option<&T>` (an optional input), \
+                            `&mut T` (an output) or `&ProcessContext`";
+
+    let reference = match ty {
+        Type::Reference(reference) => reference,
+        Type::Path(path) => {
+            if let Some(inner) = option_argument(path) {
+                return match inner {
+                    Type::Reference(TypeReference { mutability: None, elem, .. }) => {
+                        check_socket_type(elem)?;
+                        Ok(Argument::OptionalInput(elem))
+                    }
+                    Type::Reference(TypeReference { mutability: Some(mutability), .. }) => Err(Error::new_spanned(
+                        mutability,
+                        "outputs can't be optional, they're always written to\n\
+                         help: take `&mut T` instead",
+                    )),
+                    _ => Err(Error::new_spanned(
+                        inner,
+                        format!("optional inputs are taken by reference, like `Option<&T>`\n{EXPECTED}"),
+                    )),
+                };
+            }
+            return Err(Error::new_spanned(
+                ty,
+                format!("sockets are passed by reference\n{EXPECTED}"),
+            ));
+        }
+        _ => return Err(Error::new_spanned(ty, format!("unsupported argument type\n{EXPECTED}"))),
+    };
+
+    if reference.mutability.is_none() && is_process_context(&reference.elem) {
+        return Ok(Argument::Context);
+    }
+    check_socket_type(&reference.elem)?;
+    Ok(match reference.mutability {
+        Some(_) => Argument::Output(&reference.elem),
+        None => Argument::Input(&reference.elem),
+    })
+}
+
+/// Rejects socket types that can't be stored in a socket.
+fn check_socket_type(ty: &Type) -> syn::Result<()> {
+    match ty {
+        Type::Reference(TypeReference { mutability: Some(mutability), .. }) => Err(Error::new_spanned(
+            mutability,
+            "a socket can't carry a mutable reference, since every input connected to it reads it at once\n\
+             help: carry the value itself, or a shared reference `&T`",
+        )),
+        Type::Reference(reference) => check_socket_type(&reference.elem),
+        Type::ImplTrait(_) | Type::Infer(_) => Err(Error::new_spanned(
+            ty,
+            "socket types must be spelled out, so they can be checked when connecting",
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// If `path` is `Option<T>`, returns `T`.
+fn option_argument(path: &TypePath) -> Option<&Type> {
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return None;
+    };
+    match arguments.args.first()? {
+        GenericArgument::Type(ty) if arguments.args.len() == 1 => Some(ty),
+        _ => None,
+    }
+}
+
+/// Whether a type names `ProcessContext`, in which case the argument
+/// receives the context of the current run instead of being an input.
+fn is_process_context(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "ProcessContext"),
+        _ => false,
+    }
+}
+
+/// What a `process` argument says about its socket, besides its type.
+///
+/// The name comes from the argument's pattern, the rest from an optional
+/// `#[socket(label = "...", unit = "...", range = min..=max, default = value)]` attribute.
+struct SocketMetadata {
+    name: Option<String>,
+    label: Option<LitStr>,
+    unit: Option<LitStr>,
+    range: Option<(Expr, Expr)>,
+    default: Option<Expr>,
+}
+
+impl SocketMetadata {
+    fn parse(pat: &Pat, attributes: &[Attribute]) -> syn::Result<Self> {
+        let mut metadata = SocketMetadata {
+            name: match pat {
+                Pat::Ident(pat) => Some(pat.ident.to_string().trim_start_matches('_').to_string()),
+                _ => None,
+            },
+            label: None,
+            unit: None,
+            range: None,
+            default: None,
+        };
+
+        for attribute in attributes.iter().filter(|attribute| attribute.path().is_ident("socket")) {
+            attribute.parse_nested_meta(|meta| {
+                if meta.path.is_ident("label") {
+                    metadata.label = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("unit") {
+                    metadata.unit = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("range") {
+                    let range: ExprRange = meta.value()?.parse()?;
+                    let (Some(start), Some(end), RangeLimits::Closed(_)) =
+                        (range.start, range.end, range.limits)
+                    else {
+                        return Err(meta.error("expected a range like `0.0..=1.0`"));
+                    };
+                    metadata.range = Some((*start, *end));
+                } else if meta.path.is_ident("default") {
+                    metadata.default = Some(meta.value()?.parse()?);
+                } else {
+                    return Err(meta.error("expected `label`, `unit`, `range` or `default`"));
+                }
+                Ok(())
+            })?;
+        }
+
+        Ok(metadata)
+    }
+
+    /// The `SocketData` builder calls that attach this metadata.
+    fn builder_calls(&self) -> proc_macro2::TokenStream {
+        let name = self.name.as_ref().map(|name| quote! { .named(#name) });
+        let label = self.label.as_ref().map(|label| quote! { .with_label(#label) });
+        let unit = self.unit.as_ref().map(|unit| quote! { .with_unit(#unit) });
+        let range = self
+            .range
+            .as_ref()
+            .map(|(min, max)| quote! { .with_range((#min) as f64, (#max) as f64) });
+        quote! { #name #label #unit #range }
+    }
+}
@@ -1,5 +1,5 @@
 use std::marker::PhantomData;
-use cables_core::graph::{Graph, Node};
+use cables_core::graph::{Graph, Node, ProcessContext};
 use cables_macro::node_impl;
 
 #[test]
@@ -11,14 +11,14 @@ fn pass_by_ref() {
 
     graph.connect(a, 0, by_ref, 0).unwrap();
     
-    let mut pip = graph.compile(by_ref, 0);
-    pip.run();
+    let mut pip = graph.compile(by_ref, 0).unwrap();
+    pip.run(&ProcessContext::default());
 }
 
 struct Input<T>(T);
 
 #[node_impl(fields(value = 0))]
-impl<T: Copy + 'static> Node for Input<T> {
+impl<T: Copy + Send + 'static> Node for Input<T> {
     fn process(out: &mut T) {
         *out = value;
     }
@@ -29,7 +29,7 @@ struct ByRef<T> {
 }
 
 #[node_impl]
-impl<T: 'static> Node for ByRef<T> {
+impl<T: Sync + 'static> Node for ByRef<T> {
     fn process(input: &T, output: &mut &T) {
         *output = input
     }
@@ -0,0 +1,176 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use cables_core::audio::AudioBlock;
+use cables_core::graph::{Graph, Node, ProcessContext};
+use cables_core::param::Param;
+use cables_macro::node_impl;
+
+struct Ramp;
+
+#[node_impl]
+impl Node for Ramp {
+    fn process(context: &ProcessContext, out: &mut AudioBlock) {
+        for (offset, sample) in out.frames_mut(context).iter_mut().enumerate() {
+            *sample = (context.frame + offset as u64) as f32;
+        }
+    }
+}
+
+struct Gain(f32);
+
+#[node_impl(fields(amount = 0))]
+impl Node for Gain {
+    fn process(signal: &AudioBlock, context: &ProcessContext, out: &mut AudioBlock) {
+        for (out, sample) in out.frames_mut(context).iter_mut().zip(signal.frames(context)) {
+            *out = *sample * amount;
+        }
+    }
+}
+
+struct Last(Arc<Mutex<f32>>);
+
+#[node_impl(fields(seen = 0))]
+impl Node for Last {
+    fn process(signal: &AudioBlock, ctx: &ProcessContext) {
+        *seen.lock().unwrap() = signal[ctx.block_len - 1];
+    }
+}
+
+#[test]
+fn context_and_blocks() {
+    let mut graph = Graph::new();
+
+    let seen = Arc::new(Mutex::new(0.0));
+    let ramp = graph.insert(Ramp);
+    let gain = graph.insert(Gain(2.0));
+    let last = graph.insert(Last(seen.clone()));
+
+    graph.connect(ramp, 0, gain, 0).unwrap();
+    graph.connect(gain, 0, last, 0).unwrap();
+
+    let mut pip = graph.compile_sinks(&[last]).unwrap();
+    let mut context = ProcessContext::new(44100, 16);
+
+    pip.run(&context);
+    assert_eq!(*seen.lock().unwrap(), 30.0);
+
+    context.advance();
+    pip.run(&context);
+    assert_eq!(*seen.lock().unwrap(), 62.0);
+
+    // A stopped transport stays put.
+    context.playing = false;
+    context.advance();
+    pip.run(&context);
+    assert_eq!(*seen.lock().unwrap(), 62.0);
+}
+
+struct Level(Param);
+
+#[node_impl(fields(level = 0))]
+impl Node for Level {
+    fn process(context: &ProcessContext, out: &mut f32) {
+        *out = level.next_block(context) as f32;
+    }
+}
+
+struct Record(Arc<Mutex<f32>>);
+
+#[node_impl(fields(seen = 0))]
+impl Node for Record {
+    fn process(value: &f32) {
+        *seen.lock().unwrap() = *value;
+    }
+}
+
+#[test]
+fn parameters_from_fields() {
+    let mut graph = Graph::new();
+
+    let knob = Param::new(1.0);
+    let seen = Arc::new(Mutex::new(0.0));
+    let level = graph.insert(Level(knob.clone()));
+    let record = graph.insert(Record(seen.clone()));
+    graph.connect(level, 0, record, 0).unwrap();
+
+    let mut pip = graph.compile_sinks(&[record]).unwrap();
+    pip.run(&ProcessContext::default());
+    assert_eq!(*seen.lock().unwrap(), 1.0);
+
+    knob.set(0.25);
+    pip.run(&ProcessContext::default());
+    assert_eq!(*seen.lock().unwrap(), 0.25);
+}
+
+struct Phasor(f32);
+
+#[node_impl(fields(step = 0), state(phase = 0.0f32))]
+impl Node for Phasor {
+    fn process(out: &mut f32) {
+        *out = phase;
+        phase = (phase + step) % 1.0;
+    }
+}
+
+#[test]
+fn state_is_kept_and_reset() {
+    let mut graph = Graph::new();
+
+    let seen = Arc::new(Mutex::new(0.0));
+    let phasor = graph.insert(Phasor(0.25));
+    let record = graph.insert(Record(seen.clone()));
+    graph.connect(phasor, 0, record, 0).unwrap();
+
+    let mut pip = graph.compile_sinks(&[record]).unwrap();
+    let context = ProcessContext::default();
+    for _ in 0..3 {
+        pip.run(&context);
+    }
+    assert_eq!(*seen.lock().unwrap(), 0.5);
+
+    pip.reset();
+    pip.run(&context);
+    assert_eq!(*seen.lock().unwrap(), 0.0);
+    pip.run(&context);
+    assert_eq!(*seen.lock().unwrap(), 0.25);
+
+    // Resetting the graph resets the pipelines compiled from it, too.
+    graph.reset();
+    pip.run(&context);
+    assert_eq!(*seen.lock().unwrap(), 0.0);
+}
+
+/// Counts its runs in a counter it shares with the bound function.
+struct Counter(Arc<AtomicU32>);
+
+#[node_impl(fields(count = 0))]
+impl Node for Counter {
+    fn process(out: &mut f32) {
+        *out = count.fetch_add(1, Ordering::Relaxed) as f32;
+    }
+
+    fn reset(&mut self) {
+        self.0.store(0, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn nodes_reset_their_own_state() {
+    let mut graph = Graph::new();
+
+    let seen = Arc::new(Mutex::new(0.0));
+    let counter = graph.insert(Counter(Arc::new(AtomicU32::new(0))));
+    let record = graph.insert(Record(seen.clone()));
+    graph.connect(counter, 0, record, 0).unwrap();
+
+    let mut pip = graph.compile_sinks(&[record]).unwrap();
+    let context = ProcessContext::default();
+    for _ in 0..3 {
+        pip.run(&context);
+    }
+    assert_eq!(*seen.lock().unwrap(), 2.0);
+
+    graph.reset();
+    pip.run(&context);
+    assert_eq!(*seen.lock().unwrap(), 0.0);
+}
@@ -1,6 +1,6 @@
 use std::iter::Map;
 use std::ops::Range;
-use cables_core::graph::{Graph, Node};
+use cables_core::graph::{CompileError, Graph, Node, ProcessContext};
 use cables_macro::node_impl;
 
 struct Source(Range<i32>);
@@ -25,9 +25,9 @@ struct Sink;
 
 #[node_impl]
 impl Node for Sink {
-    fn process(input: &mut Map<Range<i32>, fn(i32)->i32>) {
+    fn process(input: &Map<Range<i32>, fn(i32)->i32>) {
         println!("Sinkin' it up!");
-        while let Some(val) = input.next() {
+        for val in input.clone() {
             println!("{}", val);
         }
     }
@@ -44,6 +44,24 @@ fn monadic() {
     graph.connect(source, 0, double, 0).unwrap();
     graph.connect(double, 0, sink, 0).unwrap();
 
-    let mut pip = graph.compile(sink, 0);
-    pip.run();
-}
\ No newline at end of file
+    let mut pip = graph.compile_sinks(&[sink]).unwrap();
+    pip.run(&ProcessContext::default());
+}
+
+#[test]
+fn uninit_outputs_cant_feed_back() {
+    let mut graph = Graph::new();
+
+    let source = graph.insert(Source(0..10));
+    let double = graph.insert(Double);
+    let sink = graph.insert(Sink);
+
+    // A `Map` has no default, so there's nothing for the loop to read on the first run.
+    graph.connect(source, 0, double, 0).unwrap();
+    graph.connect_feedback(double, 0, sink, 0).unwrap();
+
+    assert_eq!(
+        graph.compile_sinks(&[sink]).err(),
+        Some(CompileError::UninitFeedback { node: double, socket: 0 })
+    );
+}
@@ -1,7 +1,7 @@
 use std::f32::consts::TAU;
 use std::iter::Map;
 use std::ops::Range;
-use cables_core::graph::{Graph, Node};
+use cables_core::graph::{Graph, Node, ProcessContext};
 use cables_macro::node_impl;
 
 struct Tick(Range<i32>);
@@ -29,7 +29,7 @@ struct Sink;
 
 #[node_impl]
 impl Node for Sink {
-    fn process(input: &mut Map<Range<i32>, fn(i32)->f32>) {
+    fn process(input: &Map<Range<i32>, fn(i32)->f32>) {
         let spec = hound::WavSpec {
             channels: 1,
             sample_rate: 44100,
@@ -60,6 +60,6 @@ fn monadic() {
     graph.connect(ticker, 0, sine, 0).unwrap();
     graph.connect(sine, 0, sink, 0).unwrap();
 
-    let mut pip = graph.compile(sink, 0);
-    pip.run();
+    let mut pip = graph.compile_sinks(&[sink]).unwrap();
+    pip.run(&ProcessContext::default());
 }
\ No newline at end of file
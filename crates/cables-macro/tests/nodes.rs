@@ -1,5 +1,5 @@
 use cables_core::graph::Node;
-use cables_core::graph::{Graph, SocketData};
+use cables_core::graph::{Graph, ProcessContext, SocketData};
 use cables_macro::node_impl;
 
 #[test]
@@ -15,8 +15,8 @@ fn test_node() {
     graph.connect(b, 0, ab, 1).unwrap();
     graph.connect(ab, 0, print, 0).unwrap();
 
-    let mut pip = graph.compile(print, 0);
-    pip.run();
+    let mut pip = graph.compile_sinks(&[print]).unwrap();
+    pip.run(&ProcessContext::default());
 }
 
 struct Add;
@@ -0,0 +1,120 @@
+use std::sync::{Arc, Mutex};
+use cables_core::graph::{ConnectError, Graph, Node, ProcessContext};
+use cables_macro::node_impl;
+
+struct Constant(f32);
+
+#[node_impl(fields(value = 0))]
+impl Node for Constant {
+    fn process(#[socket(label = "Value")] out: &mut f32) {
+        *out = value;
+    }
+}
+
+struct Filter;
+
+#[node_impl(name = "Low-pass Filter", category = "Filters", description = "Lets low frequencies through.")]
+impl Node for Filter {
+    fn process(
+        signal: &f32,
+        #[socket(label = "Cutoff", unit = "Hz", range = 20..=20000, default = 1000.0)] cutoff: &f32,
+        out: &mut f32,
+    ) {
+        *out = *signal * 0.0 + *cutoff;
+    }
+}
+
+struct Record(Arc<Mutex<f32>>);
+
+#[node_impl(fields(seen = 0))]
+impl Node for Record {
+    fn process(value: &f32) {
+        *seen.lock().unwrap() = *value;
+    }
+}
+
+#[test]
+fn named_sockets() {
+    let filter = Filter;
+    let signal = filter.input_socket(0).unwrap();
+    let cutoff = filter.input_socket(1).unwrap();
+    assert_eq!(signal.name(), Some("signal"));
+    assert_eq!(signal.label(), Some("signal"));
+    assert!(!signal.has_default());
+    assert_eq!(cutoff.label(), Some("Cutoff"));
+    assert_eq!(cutoff.unit(), Some("Hz"));
+    assert_eq!(cutoff.range(), Some((20.0, 20000.0)));
+    assert!(cutoff.has_default());
+    assert_eq!(filter.output_socket(0).unwrap().name(), Some("out"));
+
+    let mut graph = Graph::new();
+    let seen = Arc::new(Mutex::new(0.0));
+    let constant = graph.insert(Constant(2.0));
+    let filter = graph.insert(Filter);
+    let record = graph.insert(Record(seen.clone()));
+
+    graph.connect(constant, "out", filter, "signal").unwrap();
+    graph.connect(filter, "out", record, 0).unwrap();
+    assert_eq!(
+        graph.connect(constant, "out", filter, "resonance"),
+        Err(ConnectError::NoSuchSocketName { node: filter, name: "resonance".to_string() })
+    );
+
+    // The cutoff is left unconnected, so it reads its default.
+    graph.compile_sinks(&[record]).unwrap().run(&ProcessContext::default());
+    assert_eq!(*seen.lock().unwrap(), 1000.0);
+
+    assert!(graph.disconnect(filter, "signal").is_some());
+    assert!(graph.disconnect(filter, "signal").is_none());
+}
+
+struct Add;
+
+#[node_impl]
+impl Node for Add {
+    fn process(a: &f32, b: Option<&f32>, out: &mut f32) {
+        *out = *a + b.copied().unwrap_or(100.0);
+    }
+}
+
+#[test]
+fn optional_inputs() {
+    assert!(Add.input_socket(1).unwrap().is_optional());
+    assert!(!Add.input_socket(0).unwrap().is_optional());
+
+    let mut graph = Graph::new();
+    let seen = Arc::new(Mutex::new(0.0));
+    let one = graph.insert(Constant(1.0));
+    let two = graph.insert(Constant(2.0));
+    let add = graph.insert(Add);
+    let record = graph.insert(Record(seen.clone()));
+    graph.connect(one, "out", add, "a").unwrap();
+    graph.connect(add, "out", record, "value").unwrap();
+
+    let mut pip = graph.compile_sinks(&[record]).unwrap();
+    pip.run(&ProcessContext::default());
+    assert_eq!(*seen.lock().unwrap(), 101.0);
+
+    graph.connect(two, "out", add, "b").unwrap();
+    pip.recompile(&graph).unwrap();
+    pip.run(&ProcessContext::default());
+    assert_eq!(*seen.lock().unwrap(), 3.0);
+}
+
+#[test]
+fn descriptors() {
+    let filter = Filter.descriptor();
+    assert_eq!(filter.name, "Low-pass Filter");
+    assert_eq!(filter.category, "Filters");
+    assert_eq!(filter.description, "Lets low frequencies through.");
+    assert_eq!(filter.inputs.len(), 2);
+    assert_eq!(filter.inputs[1].label, Some("Cutoff"));
+    assert_eq!(filter.inputs[1].unit, Some("Hz"));
+    assert_eq!(filter.outputs[0].name, Some("out"));
+
+    // Without attributes, the node is named after its type.
+    let add = Add.descriptor();
+    assert_eq!(add.name, "Add");
+    assert_eq!(add.category, cables_core::descriptor::UNCATEGORIZED);
+    assert!(add.inputs[1].optional);
+}
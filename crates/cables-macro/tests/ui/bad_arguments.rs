@@ -0,0 +1,11 @@
+use cables_core::graph::Node;
+use cables_macro::node_impl;
+
+struct Gain;
+
+#[node_impl]
+impl Node for Gain {
+    fn process(signal: f32, amount: &&mut f32, out: Option<&mut f32>) {}
+}
+
+fn main() {}
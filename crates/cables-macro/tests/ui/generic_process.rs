@@ -0,0 +1,13 @@
+use cables_core::graph::Node;
+use cables_macro::node_impl;
+
+struct Pass;
+
+#[node_impl]
+impl Node for Pass {
+    fn process<T: Copy>(input: &T, out: &mut T) {
+        *out = *input;
+    }
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use cables_core::graph::Node;
+use cables_macro::node_impl;
+
+struct Gain;
+
+#[node_impl]
+impl Node for Gain {}
+
+fn main() {}
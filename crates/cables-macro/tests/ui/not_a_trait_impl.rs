@@ -0,0 +1,12 @@
+use cables_macro::node_impl;
+
+struct Gain;
+
+#[node_impl]
+impl Gain {
+    fn process(signal: &f32, out: &mut f32) {
+        *out = *signal;
+    }
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+use cables_macro::node_impl;
+
+trait Processor {}
+
+struct Gain;
+
+#[node_impl]
+impl Processor for Gain {
+    fn process(signal: &f32, out: &mut f32) {
+        *out = *signal;
+    }
+}
+
+fn main() {}
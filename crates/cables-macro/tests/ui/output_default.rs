@@ -0,0 +1,13 @@
+use cables_core::graph::Node;
+use cables_macro::node_impl;
+
+struct Double;
+
+#[node_impl]
+impl Node for Double {
+    fn process(input: &f32, #[socket(default = 1.0)] out: &mut f32) {
+        *out = *input * 2.0;
+    }
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use cables_core::graph::Node;
+use cables_macro::node_impl;
+
+struct Double;
+
+#[node_impl]
+impl Node for Double {
+    fn process(input: &f32) -> f32 {
+        *input * 2.0
+    }
+}
+
+fn main() {}
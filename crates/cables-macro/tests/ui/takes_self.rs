@@ -0,0 +1,13 @@
+use cables_core::graph::Node;
+use cables_macro::node_impl;
+
+struct Gain(f32);
+
+#[node_impl]
+impl Node for Gain {
+    fn process(&self, signal: &f32, out: &mut f32) {
+        *out = *signal * self.0;
+    }
+}
+
+fn main() {}
@@ -15,6 +15,8 @@ impl Plugin for MusicStd {
                 "Default library containing lots of audio and musical functionality.".to_string(),
             ),
             authors: vec!["Overtone".to_string()],
+            version: Version::parse(env!("CARGO_PKG_VERSION")).unwrap(),
+            requires: vec![],
         }
     }
 
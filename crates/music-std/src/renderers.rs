@@ -3,6 +3,7 @@ use {
     overtone::{
         project::composition::Composition,
         renderer::{RenderResult, Renderer},
+        task::{self, BoxTask},
     },
     std::collections::HashMap,
 };
@@ -23,10 +24,15 @@ pub fn get() -> HashMap<String, Box<dyn Renderer>> {
 pub struct AudioPCMRenderer {}
 
 impl Renderer for AudioPCMRenderer {
-    fn render(&self, composition: &Composition /* fragment slice */) -> Box<dyn RenderResult> {
-        let audio_pcm = AudioPcm::example();
+    fn render<'a>(
+        &'a self,
+        composition: &'a Composition, /* fragment slice */
+    ) -> BoxTask<'a, Box<dyn RenderResult>> {
+        Box::pin(task::from_fn(|_progress| {
+            let audio_pcm = AudioPcm::example();
 
-        Box::new(audio_pcm)
+            Box::new(audio_pcm) as Box<dyn RenderResult>
+        }))
     }
 
     fn get_render_format_id(&self) -> String {
@@ -1,9 +1,8 @@
-use overtone::transformer::{NodeRef, Sink, Source, Value};
-use std::any::Any;
+use overtone::task;
+use overtone::transformer::{Node, NodeRef, ProductionSetup, PullNode};
 use std::sync::{Arc, RwLock};
-use std::time::SystemTime;
-use overtone::transformer::Node;
 
+use audio::AudioChunkPcm;
 use nodes::combine::CombineNode;
 use nodes::exporter_wav::WAVExporter;
 use nodes::gain::GainNode;
@@ -12,50 +11,57 @@ use nodes::wave_generator::WaveGenerator;
 pub mod audio;
 pub mod nodes;
 
+const SAMPLE_RATE: u32 = 44100;
+
 fn new_node<N: Node + 'static>(node: N) -> NodeRef {
     Arc::new(RwLock::new(node))
 }
 
-macro_rules! connect {
-    ($a:expr, $a_out:expr, $b_in:expr, $b:expr) => {{
-        $b.write().unwrap().connect($b_in, $a, $a_out.clone()).unwrap()
-    }};
+fn generator(frequency: f32) -> PullNode {
+    PullNode::new(new_node(WaveGenerator::new(frequency, SAMPLE_RATE)))
+        .output::<AudioChunkPcm>("out")
 }
 
-macro_rules! drain {
-    ($a:expr) => {{
-        ($a).write()
-            .unwrap()
-            .as_sink()
-            .expect("Node could not be converted to a Sink.")
-            .drain()
-            .expect("Couldn't drain the sink.");
-    }}
+fn combine() -> PullNode {
+    PullNode::new(new_node(CombineNode::new()))
+        .input::<AudioChunkPcm>("a")
+        .input::<AudioChunkPcm>("b")
+        .output::<AudioChunkPcm>("out")
 }
 
 fn main() {
     let base = 261.63/2.0;
+    let mut setup = ProductionSetup::new("Chord");
+    setup.register_format::<AudioChunkPcm>();
 
-    let a = new_node(WaveGenerator::new(base));
-    let b = new_node(WaveGenerator::new(base * 5.0 / 4.0));
-    let c = new_node(WaveGenerator::new(base * 3.0 / 2.0));
+    let a = setup.insert(generator(base));
+    let b = setup.insert(generator(base * 5.0 / 4.0));
+    let c = setup.insert(generator(base * 3.0 / 2.0));
 
-    let ab = new_node(CombineNode::new());
-    let abc = new_node(CombineNode::new());
+    let ab = setup.insert(combine());
+    let abc = setup.insert(combine());
 
-    let master_gain = new_node(GainNode::new(0.25));
+    let master_gain = setup.insert(
+        PullNode::new(new_node(GainNode::new(0.25)))
+            .input::<AudioChunkPcm>("in")
+            .output::<AudioChunkPcm>("out"),
+    );
 
     let export_path = "./examples/production_graph/exports/";
-    let wav_exporter = new_node(WAVExporter::new(export_path));
+    let wav_exporter = setup.insert(
+        PullNode::new(new_node(WAVExporter::new(export_path)))
+            .input::<AudioChunkPcm>("in"),
+    );
 
-    connect!(  a, 0, 0,  ab);
-    connect!(  b, 0, 1,  ab);
-    connect!( ab, 0, 0, abc);
-    connect!(  c, 0, 1, abc);
-    connect!(abc, 0, 0, master_gain);
-    connect!(master_gain, 0, 0, wav_exporter);
+    setup.connect(  a, "out", ab, "a").unwrap();
+    setup.connect(  b, "out", ab, "b").unwrap();
+    setup.connect( ab, "out", abc, "a").unwrap();
+    setup.connect(  c, "out", abc, "b").unwrap();
+    setup.connect(abc, "out", master_gain, "in").unwrap();
+    setup.connect(master_gain, "out", wav_exporter, "in").unwrap();
 
-    drain!(wav_exporter);
+    let drain = setup.drain().expect("Couldn't compile the setup.");
+    task::block_on(drain).expect("Couldn't drain the sink.");
 
     println!("Wrote result to '{}'.", export_path);
-}
\ No newline at end of file
+}
@@ -1,6 +1,6 @@
-use overtone::transformer::{Node, NodeRef, SocketConnectionError, SocketIdx, SocketRef, Source};
+use overtone::transformer::{Node, NodeRef, SocketConnectionError, SocketIdx, SocketRef, Source, StreamInfo};
 use std::any::Any;
-use crate::audio::{AudioChunkPcm, AudioPcm};
+use crate::audio::AudioChunkPcm;
 
 pub struct CombineNode {
     source1: Option<SocketRef>,
@@ -56,17 +56,21 @@ impl Node for CombineNode {
         impl Source for InnerSource {
             type Item = AudioChunkPcm;
 
-            fn pull(&mut self) -> Self::Item {
-                let frame1 = self.source1.pull();
-                let frame2 = self.source2.pull();
-                AudioChunkPcm {
+            fn pull(&mut self) -> Option<Self::Item> {
+                let frame1 = self.source1.pull()?;
+                let frame2 = self.source2.pull()?;
+                Some(AudioChunkPcm {
                     content: frame1
                         .content
                         .iter()
                         .zip(frame2.content.iter())
                         .map(|(a, b)| *a + *b)
                         .collect(),
-                }
+                })
+            }
+
+            fn info(&self) -> StreamInfo {
+                self.source1.info()
             }
         }
 
@@ -1,11 +1,11 @@
 use std::path::{Path, PathBuf};
-use overtone::transformer::{ExportError, Node, NodeRef, Sink, SocketConnectionError, SocketIdx, SocketRef, Source};
+use overtone::task::Progress;
+use overtone::transformer::{ExportError, Node, NodeRef, Sink, SocketConnectionError, SocketIdx, SocketRef};
 use std::any::Any;
-use crate::audio::{AudioChunkPcm, AudioPcm};
+use crate::audio::AudioChunkPcm;
 
 pub struct WAVExporter {
     file: PathBuf,
-    sample_rate: usize,
     source: Option<SocketRef>,
 }
 
@@ -13,7 +13,6 @@ impl WAVExporter {
     pub fn new<P: AsRef<Path>>(path: P) -> Self {
         WAVExporter {
             file: PathBuf::from(path.as_ref()),
-            sample_rate: 41000,
             source: None,
         }
     }
@@ -47,34 +46,52 @@ impl Node for WAVExporter {
 }
 
 impl Sink for WAVExporter {
-    fn drain(&mut self) -> Result<(), ExportError> {
+    fn drain(&mut self, progress: &Progress) -> Result<(), ExportError> {
         let location = &self.file;
 
-        let &SocketRef (ref node_ref, socket_idx) = self.source.as_ref().unwrap();
-        let mut node_ref = node_ref.write().unwrap();
-        let mut source = node_ref.try_get_source(socket_idx).unwrap();
+        let mut source = ExportError::source::<AudioChunkPcm>(self.source.as_ref(), 0)?;
 
-        let audio_pcm: AudioChunkPcm = source.pull();
+        let info = source.info();
         let spec = hound::WavSpec {
-            channels: 1,
-            sample_rate: 41000,
+            channels: info.channels,
+            sample_rate: info.sample_rate,
             bits_per_sample: 16,
             sample_format: hound::SampleFormat::Int,
         };
 
         let location = location.join(format!("export-{}.wav", chrono::Utc::now().to_string()));
 
-        let mut writer = hound::WavWriter::create(location, spec).expect("Failed to write.");
+        let mut writer = hound::WavWriter::create(location, spec).map_err(wav_error)?;
 
-        for sample in audio_pcm.content.iter().copied() {
-            let sample16: i16 = (sample * (i16::MAX as f32)) as i16;
-            writer.write_sample(sample16).unwrap();
+        // Write each chunk as it comes, so long streams don't pile up in memory.
+        let mut written = 0;
+        while let Some(chunk) = source.pull() {
+            if progress.is_cancelled() {
+                return Err(ExportError::Cancelled);
+            }
+            for sample in chunk.content.iter().copied() {
+                let sample16: i16 = (sample * (i16::MAX as f32)) as i16;
+                writer.write_sample(sample16).map_err(wav_error)?;
+            }
+
+            written += chunk.content.len() as u64 / info.channels as u64;
+            if let Some(length) = info.length {
+                progress.set(written as f32 / length as f32);
+            }
+        }
+        if progress.is_cancelled() {
+            return Err(ExportError::Cancelled);
         }
 
-        writer
-            .finalize()
-            .expect("Error finalising to write the wav file.");
+        writer.finalize().map_err(wav_error)?;
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+fn wav_error(error: hound::Error) -> ExportError {
+    match error {
+        hound::Error::IoError(error) => ExportError::IO(error),
+        error => ExportError::IO(std::io::Error::other(error)),
+    }
+}
@@ -1,6 +1,6 @@
-use overtone::transformer::{Node, NodeRef, SocketConnectionError, SocketIdx, SocketRef, Source};
+use overtone::transformer::{Node, NodeRef, SocketConnectionError, SocketIdx, SocketRef, Source, StreamInfo};
 use std::any::Any;
-use crate::audio::{AudioChunkPcm, AudioPcm};
+use crate::audio::AudioChunkPcm;
 
 pub struct GainNode {
     gain: f32,
@@ -43,12 +43,16 @@ impl Node for GainNode {
         impl Source for InnerSource {
             type Item = AudioChunkPcm;
 
-            fn pull(&mut self) -> Self::Item {
-                let mut frame = self.source.pull();
+            fn pull(&mut self) -> Option<Self::Item> {
+                let mut frame = self.source.pull()?;
                 for sample in frame.content.iter_mut() {
                     *sample *= self.gain;
                 }
-                frame
+                Some(frame)
+            }
+
+            fn info(&self) -> StreamInfo {
+                self.source.info()
             }
         }
 
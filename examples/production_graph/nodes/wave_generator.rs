@@ -1,14 +1,16 @@
-use overtone::transformer::{Node, NodeRef, SocketConnectionError, SocketIdx, Source};
+use overtone::transformer::{Node, NodeRef, SocketConnectionError, SocketIdx, Source, StreamInfo};
 use std::any::Any;
-use crate::audio::{AudioChunkPcm, AudioPcm};
+use crate::audio::{pluck, AudioChunkPcm, CHUNK_FRAMES};
 
+/// Plucks a note and streams it for a second.
 pub struct WaveGenerator {
     frequency: f32,
+    sample_rate: u32,
 }
 
 impl WaveGenerator {
-    pub fn new(frequency: f32) -> Self {
-        WaveGenerator { frequency }
+    pub fn new(frequency: f32, sample_rate: u32) -> Self {
+        WaveGenerator { frequency, sample_rate }
     }
 }
 
@@ -32,19 +34,40 @@ impl Node for WaveGenerator {
         }
         pub struct InnerSource {
             frequency: f32,
+            sample_rate: u32,
+            frame: usize,
         }
         impl Source for InnerSource {
             type Item = AudioChunkPcm;
 
-            fn pull(&mut self) -> Self::Item {
-                let whole = AudioPcm::example(self.frequency);
-                AudioChunkPcm { content: whole.content }
+            fn pull(&mut self) -> Option<Self::Item> {
+                let length = self.sample_rate as usize;
+                if self.frame >= length {
+                    return None;
+                }
+
+                let end = (self.frame + CHUNK_FRAMES).min(length);
+                let content = (self.frame..end)
+                    .map(|i| pluck(self.frequency, i as f32 / self.sample_rate as f32))
+                    .collect();
+                self.frame = end;
+                Some(AudioChunkPcm { content })
+            }
+
+            fn info(&self) -> StreamInfo {
+                StreamInfo {
+                    sample_rate: self.sample_rate,
+                    channels: 1,
+                    length: Some(self.sample_rate as u64),
+                }
             }
         }
         let audio_source = InnerSource {
             frequency: self.frequency,
+            sample_rate: self.sample_rate,
+            frame: 0,
         };
         let audio_source: Box<dyn Source<Item = AudioChunkPcm>> = Box::new(audio_source);
         Ok(Box::new(audio_source))
     }
-}
\ No newline at end of file
+}
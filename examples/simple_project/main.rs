@@ -4,24 +4,14 @@ use overtone::{project::Project, OvertoneError};
 fn main() -> Result<(), OvertoneError> {
     let mut _p = Project::load_from_directory("./examples/simple_project/Untitled Project")?;
 
-    let plugin_ref = _p.load_plugin("music-std".to_owned())?;
+    _p.load_plugin("music-std".to_owned())?;
 
-    let renderers = plugin_ref
-        .get_plugin()
-        .get_contributions()
-        .renderers
-        .expect("Plugin does not have renderer contributions.");
-    let renderer = renderers
-        .get("audio-pcm-renderer")
+    let contributions = _p.plugin_host.contributions();
+    let renderer = contributions
+        .renderer("audio-pcm-renderer")
         .expect("Renderer not found.");
-
-    let exporters = plugin_ref
-        .get_plugin()
-        .get_contributions()
-        .exporters
-        .expect("Plugin does not have exporter contributions.");
-    let exporter = exporters
-        .get("pcm-wav-exporter")
+    let exporter = contributions
+        .exporter("pcm-wav-exporter")
         .expect("Exporter not found");
 
     let song = Composition::load_from_directory(
@@ -9,18 +9,18 @@ use crate::OvertoneError;
 use crate::project::Project;
 
 /// Edits an overtone Project
-pub struct Editor<'a> {
-    project: Project<'a>,
+pub struct Editor {
+    project: Project,
 }
 
-impl<'a> Editor<'a> {
-    pub fn new(project: Project<'a>) -> Self {
+impl Editor {
+    pub fn new(project: Project) -> Self {
         Editor {
             project
         }
     }
 
-    pub fn do_action(&mut self, client: &Client<'a>, action: Action) -> Result<(), OvertoneError> {
+    pub fn do_action(&mut self, client: &Client<'_>, action: Action) -> Result<(), OvertoneError> {
         todo!()
     }
 }
@@ -36,6 +36,10 @@ pub mod plugin_prelude {
     pub use crate::plugin::*;
 }
 
+/// The graph engine production setups run on, re-exported so plugins build their nodes
+/// against the same version as the host.
+pub use cables_core;
+
 /// Trait that allows extracting some metadata from foreign types.
 pub trait Info {
     fn get_name(&self) -> &str;
@@ -57,6 +61,7 @@ pub enum OvertoneError {
 
     ProjectError(project::ProjectError),
     CompositionError(project::composition::CompositionError),
+    ProductionError(transformer::ProductionError),
     PluginError(crate::plugin::PluginError),
 }
 
@@ -0,0 +1,156 @@
+//! # Plugin Host
+//!
+//! The [`PluginHost`] owns the plugins a project has loaded, along with everything
+//! they contribute with.
+//!
+//! Contributions are objects whose code lives in a plugin's library, so the host makes
+//! sure they are dropped before the libraries are closed.
+
+use super::{
+    ContributionRegistry, LoadedPlugin, PluginContributions, PluginError, PluginMetadata,
+    PluginWatcher,
+};
+use cables_core::registry::NodeRegistry;
+use std::time::Duration;
+
+/// The plugins a project has loaded, and their contributions.
+#[derive(Debug, Default)]
+pub struct PluginHost {
+    contributions: ContributionRegistry,
+
+    // This must be declared last
+    // as the contributions need to be dropped before the libraries close.
+    plugins: Vec<LoadedPlugin>,
+}
+
+impl PluginHost {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the loaded plugin with this id, if there's one.
+    pub fn get(&self, id: &str) -> Option<&LoadedPlugin> {
+        self.plugins.iter().find(|p| p.id == id)
+    }
+
+    pub fn is_loaded(&self, id: &str) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// Every loaded plugin, in the order they were loaded.
+    pub fn plugins(&self) -> &[LoadedPlugin] {
+        &self.plugins
+    }
+
+    /// Everything the loaded plugins contribute with.
+    pub fn contributions(&self) -> &ContributionRegistry {
+        &self.contributions
+    }
+
+    /// The metadata of every loaded plugin.
+    pub fn metadata(&self) -> Vec<PluginMetadata> {
+        self.plugins
+            .iter()
+            .map(|p| p.get_plugin().get_metadata())
+            .collect()
+    }
+
+    /// Checks that a freshly loaded plugin can be taken in with these contributions:
+    /// it isn't loaded already, and its contributions don't collide with anything registered
+    /// (see [`ContributionRegistry::check`]).
+    pub fn check(
+        &self,
+        plugin: &LoadedPlugin,
+        contributions: &PluginContributions,
+        node_registry: &NodeRegistry,
+    ) -> Result<(), PluginError> {
+        if self.is_loaded(&plugin.id) {
+            return Err(PluginError::PluginAlreadyLoaded());
+        }
+        self.contributions
+            .check(&plugin.id, contributions, node_registry)
+    }
+
+    /// Checks that a new build of a loaded plugin can take the old one's place: its contributions
+    /// can reuse the old build's ids, but mustn't collide with anything else.
+    pub fn check_replacement(
+        &self,
+        plugin: &LoadedPlugin,
+        contributions: &PluginContributions,
+        node_registry: &NodeRegistry,
+    ) -> Result<(), PluginError> {
+        if !self.is_loaded(&plugin.id) {
+            return Err(PluginError::PluginNotLoaded(plugin.id.clone()));
+        }
+        self.contributions
+            .check(&plugin.id, contributions, node_registry)
+    }
+
+    /// Takes a freshly loaded plugin in, along with the contributions it gave, and registers them.
+    ///
+    /// If that can't be done (see [`PluginHost::check`]), the contributions are dropped,
+    /// and then the plugin (closing its library).
+    pub fn insert(
+        &mut self,
+        plugin: LoadedPlugin,
+        contributions: PluginContributions,
+        node_registry: &NodeRegistry,
+    ) -> Result<&LoadedPlugin, PluginError> {
+        if let Err(error) = self.check(&plugin, &contributions, node_registry) {
+            drop(contributions);
+            return Err(error);
+        }
+
+        self.contributions
+            .add(&plugin.id, contributions, node_registry)?;
+        self.plugins.push(plugin);
+        Ok(self.plugins.last().unwrap())
+    }
+
+    /// Drops a plugin's contributions and then the plugin itself, closing its library.
+    ///
+    /// # Safety
+    ///
+    /// Anything built from the plugin's contributions outside the host, like the nodes
+    /// of a production setup or node types registered elsewhere, must have been dropped
+    /// already: their code lives in the library that's closed.
+    pub unsafe fn remove(&mut self, id: &str) -> Result<(), PluginError> {
+        let index = self
+            .plugins
+            .iter()
+            .position(|p| p.id == id)
+            .ok_or_else(|| PluginError::PluginNotLoaded(id.to_string()))?;
+
+        self.contributions.remove(id);
+        self.plugins.remove(index);
+        Ok(())
+    }
+
+    /// The ids of the loaded plugins that require this one.
+    pub fn dependents(&self, id: &str) -> Vec<String> {
+        self.metadata()
+            .into_iter()
+            .filter(|metadata| metadata.requires.iter().any(|r| r.id == id))
+            .map(|metadata| metadata.id)
+            .collect()
+    }
+
+    /// The ids of the loaded plugins whose library changed on disk since they were loaded.
+    ///
+    /// See [`PluginWatcher`] to be told as it happens.
+    pub fn changed_on_disk(&self) -> Vec<String> {
+        self.plugins
+            .iter()
+            .filter(|p| p.has_changed_on_disk())
+            .map(|p| p.id.clone())
+            .collect()
+    }
+
+    /// Starts watching the libraries of the loaded plugins for rebuilds,
+    /// checking them every `interval` (see [`PluginWatcher`]).
+    ///
+    /// Plugins loaded afterwards aren't watched.
+    pub fn watch(&self, interval: Duration) -> PluginWatcher {
+        PluginWatcher::new(&self.plugins, interval)
+    }
+}
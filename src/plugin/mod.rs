@@ -8,21 +8,33 @@
 //! A [`Plugin`] comes with metadata and offers [`PluginContributions`]
 //! that can offer specific kinds of functionalities.
 
+pub mod host;
+pub mod registry;
+pub mod watcher;
+
 use super::project::Project;
 use crate::renderer::RenderExporter;
 use crate::renderer::Renderer;
-use crate::transformer::Node;
+use cables_core::registry::{NodeRegistry, NodeType};
 use crate::OvertoneError;
 use libloading::Library;
+pub use host::PluginHost;
+pub use registry::ContributionRegistry;
+pub use watcher::PluginWatcher;
+pub use semver::{Version, VersionReq};
 use serde_derive::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt::Debug;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::SystemTime;
 
 #[allow(dead_code)]
 /// An Overtone plugin, which will be loaded, registered,
 /// and can contribute with Renderers, Track Fragments and more.
-pub trait Plugin {
+///
+/// Plugins are `Send` so projects, plugins and all, can be loaded on other threads.
+pub trait Plugin: Send {
     ///Returns some metadata for the plugin.
     fn get_metadata(&self) -> PluginMetadata;
 
@@ -50,9 +62,135 @@ pub struct PluginMetadata {
     pub description: Option<String>,
     /// Authors of the plugin.
     pub authors: Vec<String>,
+    /// Version of the plugin, which other plugins' requirements are checked against.
+    pub version: Version,
+    /// Plugins that must be loaded before this one, usually because this plugin
+    /// builds on their formats or contribution kinds.
+    #[serde(default)]
+    pub requires: Vec<PluginRequirement>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// A plugin that another plugin depends on, and the versions of it that will do.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PluginRequirement {
+    /// The id of the required plugin.
+    pub id: String,
+    /// The versions of the required plugin this one works with, like `^0.2`.
+    pub versions: VersionReq,
+}
+
+impl PluginRequirement {
+    pub fn new(id: impl Into<String>, versions: VersionReq) -> Self {
+        Self {
+            id: id.into(),
+            versions,
+        }
+    }
+
+    /// Checks that `found` is a version of the required plugin that will do for `plugin`.
+    fn check(&self, plugin: &str, found: &PluginMetadata) -> Result<(), PluginError> {
+        if self.versions.matches(&found.version) {
+            Ok(())
+        } else {
+            Err(PluginError::IncompatibleDependency {
+                plugin: plugin.to_string(),
+                dependency: self.id.clone(),
+                required: self.versions.clone(),
+                found: found.version.clone(),
+            })
+        }
+    }
+
+    /// Checks that the required plugin is among the `loaded` ones, in a version that will do.
+    pub(crate) fn check_loaded(
+        &self,
+        plugin: &str,
+        loaded: &[PluginMetadata],
+    ) -> Result<(), PluginError> {
+        let found = loaded
+            .iter()
+            .find(|p| p.id == self.id)
+            .ok_or_else(|| PluginError::MissingDependency {
+                plugin: plugin.to_string(),
+                dependency: self.id.clone(),
+            })?;
+        self.check(plugin, found)
+    }
+}
+
+/// Orders `pending` plugins so that every plugin comes after the ones it requires.
+///
+/// Requirements can be met by other pending plugins or by `loaded` ones. Returns indices
+/// into `pending`, or the first missing, incompatible or cyclic dependency found.
+pub fn load_order(
+    pending: &[PluginMetadata],
+    loaded: &[PluginMetadata],
+) -> Result<Vec<usize>, PluginError> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Mark {
+        Visiting,
+        Done,
+    }
+
+    struct Search<'p> {
+        pending: &'p [PluginMetadata],
+        loaded: &'p [PluginMetadata],
+        marks: Vec<Option<Mark>>,
+        path: Vec<usize>,
+        order: Vec<usize>,
+    }
+
+    impl Search<'_> {
+        fn visit(&mut self, index: usize) -> Result<(), PluginError> {
+            match self.marks[index] {
+                Some(Mark::Done) => return Ok(()),
+                Some(Mark::Visiting) => {
+                    let start = self.path.iter().position(|&i| i == index).unwrap();
+                    let cycle = self.path[start..]
+                        .iter()
+                        .chain([&index])
+                        .map(|&i| self.pending[i].id.clone())
+                        .collect();
+                    return Err(PluginError::DependencyCycle(cycle));
+                }
+                None => {}
+            }
+
+            self.marks[index] = Some(Mark::Visiting);
+            self.path.push(index);
+
+            let plugin = &self.pending[index];
+            for requirement in &plugin.requires {
+                match self.pending.iter().position(|p| p.id == requirement.id) {
+                    Some(dependency) => {
+                        requirement.check(&plugin.id, &self.pending[dependency])?;
+                        self.visit(dependency)?;
+                    }
+                    None => requirement.check_loaded(&plugin.id, self.loaded)?,
+                }
+            }
+
+            self.path.pop();
+            self.marks[index] = Some(Mark::Done);
+            self.order.push(index);
+            Ok(())
+        }
+    }
+
+    let mut search = Search {
+        pending,
+        loaded,
+        marks: vec![None; pending.len()],
+        path: Vec::new(),
+        order: Vec::with_capacity(pending.len()),
+    };
+    for index in 0..pending.len() {
+        search.visit(index)?;
+    }
+    Ok(search.order)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 /// Trait that describes a plugin from the perspective of a project.
 pub struct PluginDependencyEntry {
     pub path: PathBuf,
@@ -68,14 +206,23 @@ pub struct PluginContributions {
 /// Something that a plugin contributes with to you.
 ///
 /// A plugin can contribute with several of these.
+///
+/// Contributions are identified by ids, which must be unique across every plugin
+/// a project loads (see [`ContributionRegistry`]).
 pub enum PluginContribution {
     #[deprecated]
-    Renderer(Box<dyn Renderer>),
-    /// A 'Node' that can be used in a Production Setup.
-    Node(Box<dyn Node>),
+    Renderer {
+        id: String,
+        renderer: Box<dyn Renderer>,
+    },
+    /// A kind of node that can be used in a Production Setup.
+    Node(NodeContribution),
     /// An Exporter, which can be used to export productions
     /// of compositions.
-    Exporter(Box<dyn RenderExporter>),
+    Exporter {
+        id: String,
+        exporter: Box<dyn RenderExporter>,
+    },
     /// A new 'kind' of contribution that this plugin
     /// or other plugins can contribute with.
     ContributionKind(String),
@@ -83,6 +230,7 @@ pub enum PluginContribution {
     /// by Overtone. Use this if you plugin offers a contribution
     /// for another third-party plugin.
     Other {
+        id: String,
         /// What is the kind of this contribution
         /// as understood by that plugin?
         ///
@@ -93,36 +241,81 @@ pub enum PluginContribution {
     },
 }
 
+/// A kind of [`cables_core`] node that a plugin contributes with.
+///
+/// Registering it lets production setups that use it be saved and loaded.
+pub struct NodeContribution {
+    /// The id the node type is registered under.
+    pub type_id: &'static str,
+    register: fn(&mut NodeRegistry),
+}
+
+impl NodeContribution {
+    pub fn of<N: NodeType>() -> Self {
+        Self {
+            type_id: N::TYPE_ID,
+            register: |registry| registry.register::<N>(),
+        }
+    }
+
+    /// Registers the node type into a registry.
+    pub fn register(&self, registry: &mut NodeRegistry) {
+        (self.register)(registry)
+    }
+}
+
 /// Type that holds a plugin loaded from a foreign library, metadata,
 /// and the loaded library itself.
-pub struct LoadedPlugin<'a> {
+///
+/// Loaded plugins are kept by a [`PluginHost`].
+pub struct LoadedPlugin {
     pub id: String,
     pub plugin: Box<dyn Plugin>,
-    pub source: &'a PluginDependencyEntry,
+    /// The project's entry for this plugin, as it was when the plugin loaded.
+    pub source: PluginDependencyEntry,
+    /// Where the library was loaded from, with the project's directory resolved.
+    ///
+    /// The library is actually opened from a temporary copy of this file.
+    pub path: PathBuf,
+    /// When the library was last modified as it was loaded, to notice rebuilds.
+    modified: Option<SystemTime>,
 
-    // This must be declared last
+    // This must be declared after 'plugin'
     // as it needs to be dropped after 'plugin' drops.
     lib: Library,
+    // And this after 'lib', as the copy can only be deleted once it's closed.
+    copy: LibraryCopy,
 }
 
-impl<'a> LoadedPlugin<'a> {
+impl LoadedPlugin {
     /// Returns a reference to the [`Plugin`] itself.
     pub fn get_plugin(&self) -> &dyn Plugin {
         self.plugin.as_ref()
     }
 
     /// Returns a reference to the [`Library`] the plugin was loaded from.
-    pub fn get_lib(&'a self) -> &'a Library {
+    pub fn get_lib(&self) -> &Library {
         &self.lib
     }
 
+    /// Whether the library file was modified (usually, rebuilt) since the plugin was loaded.
+    ///
+    /// A [`PluginWatcher`] checks this from a thread of its own, so rebuilds are noticed
+    /// as they happen.
+    pub fn has_changed_on_disk(&self) -> bool {
+        modified_time(&self.path) != self.modified
+    }
+
     /// Constructor. This loads a plugin from a dependency entry (which
     /// might contain an absolute or relative path.
+    ///
+    /// The library's [`PluginFingerprint`] is checked against the host's before
+    /// anything else in it is called, and the plugin's metadata must give `id` as its id.
     pub fn load_from_dependency_entry(
         base_path: &Option<PathBuf>,
         id: &str,
-        source: &'a PluginDependencyEntry,
-    ) -> Result<LoadedPlugin<'a>, PluginError> {
+        source: &PluginDependencyEntry,
+    ) -> Result<LoadedPlugin, PluginError> {
         pub type PluginProvider = unsafe fn() -> Box<dyn Plugin>;
         pub const PLUGIN_PROVIDER_NAME: &[u8; 19] = b"get_overtone_plugin";
 
@@ -130,41 +323,184 @@ impl<'a> LoadedPlugin<'a> {
             .as_ref()
             .map_or_else(|| source.path.clone(), |b_p| b_p.join(source.path.clone()));
 
+        let modified = modified_time(&path);
+        let copy = LibraryCopy::new(id, &path)?;
         let lib: libloading::Library;
         let plugin: Box<dyn Plugin>;
         unsafe {
-            let l = libloading::Library::new(path);
+            let l = libloading::Library::new(&copy.0);
             lib = l.map_err(PluginError::LibraryNotFound)?;
+
+            let fingerprint = lib
+                .get::<*const PluginFingerprint>(PLUGIN_FINGERPRINT_NAME)
+                .map_err(|_| PluginError::LibraryIsNotOvertonePlugin())?;
+            let fingerprint = &**fingerprint;
+            if *fingerprint != PluginFingerprint::CURRENT {
+                return Err(PluginError::IncompatibleAbi {
+                    host: PluginFingerprint::CURRENT.to_abi_version(),
+                    plugin: fingerprint.to_abi_version(),
+                });
+            }
+
             let plugin_getter = lib
                 .get::<PluginProvider>(PLUGIN_PROVIDER_NAME)
                 .map_err(|_| PluginError::LibraryIsNotOvertonePlugin())?;
             plugin = plugin_getter();
         }
 
+        // Plugins are looked up by their table key, and required by their metadata's id.
+        let metadata_id = plugin.get_metadata().id;
+        if metadata_id != id {
+            // The plugin's code lives in the library.
+            drop(plugin);
+            return Err(PluginError::MismatchedId {
+                key: id.to_string(),
+                metadata: metadata_id,
+            });
+        }
+
         Ok(LoadedPlugin {
             id: id.to_string(),
             lib,
-            source,
+            source: source.clone(),
+            path,
+            modified,
             plugin,
+            copy,
         })
     }
 }
 
-impl<'a> Debug for LoadedPlugin<'a> {
+/// A temporary copy of a plugin's library, deleted when dropped.
+///
+/// Libraries are opened from a copy with a name of its own, as the system may hand back
+/// the image it already has for a path (even one that was closed) instead of reading
+/// a rebuilt file again. It also keeps a build from overwriting a library that's open.
+struct LibraryCopy(PathBuf);
+
+impl LibraryCopy {
+    fn new(id: &str, path: &Path) -> Result<Self, PluginError> {
+        static COPIES: AtomicUsize = AtomicUsize::new(0);
+
+        let directory = std::env::temp_dir().join("overtone-plugins");
+        std::fs::create_dir_all(&directory).map_err(PluginError::LibraryNotCopied)?;
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let copy = directory.join(format!(
+            "{}-{}-{}-{}",
+            id,
+            std::process::id(),
+            COPIES.fetch_add(1, Ordering::Relaxed),
+            file_name
+        ));
+        std::fs::copy(path, &copy).map_err(PluginError::LibraryNotCopied)?;
+        Ok(Self(copy))
+    }
+}
+
+impl Drop for LibraryCopy {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+impl Debug for LoadedPlugin {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(format!("[Plugin '{}']", self.plugin.get_metadata().name).as_str())
     }
 }
 
+// MARK: ABI
+
+/// The name of the symbol a plugin library exports its [`PluginFingerprint`] under.
+pub const PLUGIN_FINGERPRINT_NAME: &[u8] = b"OVERTONE_PLUGIN_FINGERPRINT";
+
+/// What a plugin library was built with: the version of overtone, the compiler,
+/// and the layout of the types the host and the plugin hand each other.
+///
+/// Rust has no stable ABI, so a plugin is only loaded if its fingerprint
+/// is the same as the host's. [`overtone_plugin!`] exports it.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PluginFingerprint {
+    /// A hash of the sizes and alignments of the types plugins share with the host.
+    ///
+    /// This comes first, as it's the field most likely to be read correctly
+    /// when the rest of the layout differs.
+    pub layout_hash: u64,
+    pub overtone_version: &'static str,
+    pub rustc_version: &'static str,
+}
+
+impl PluginFingerprint {
+    /// The fingerprint of the overtone being compiled right now.
+    pub const CURRENT: Self = Self {
+        layout_hash: layout_hash(),
+        overtone_version: env!("CARGO_PKG_VERSION"),
+        rustc_version: env!("OVERTONE_RUSTC_VERSION"),
+    };
+
+    /// Copies the fingerprint into a value that outlives the library it came from.
+    pub fn to_abi_version(&self) -> AbiVersion {
+        AbiVersion {
+            overtone_version: self.overtone_version.to_string(),
+            rustc_version: self.rustc_version.to_string(),
+            layout_hash: self.layout_hash,
+        }
+    }
+}
+
+/// An owned copy of a [`PluginFingerprint`], for reporting mismatches.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AbiVersion {
+    pub overtone_version: String,
+    pub rustc_version: String,
+    pub layout_hash: u64,
+}
+
+/// FNV-1a over the size and alignment of every type that crosses the plugin boundary.
+const fn layout_hash() -> u64 {
+    use std::mem::{align_of, size_of};
+
+    let layouts = [
+        (size_of::<Box<dyn Plugin>>(), align_of::<Box<dyn Plugin>>()),
+        (size_of::<PluginMetadata>(), align_of::<PluginMetadata>()),
+        (size_of::<PluginContributions>(), align_of::<PluginContributions>()),
+        (size_of::<PluginContribution>(), align_of::<PluginContribution>()),
+        (size_of::<NodeContribution>(), align_of::<NodeContribution>()),
+        (size_of::<NodeRegistry>(), align_of::<NodeRegistry>()),
+        (size_of::<Project>(), align_of::<Project>()),
+        (size_of::<PluginFingerprint>(), align_of::<PluginFingerprint>()),
+    ];
+
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let mut i = 0;
+    while i < layouts.len() {
+        hash ^= layouts[i].0 as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+        hash ^= layouts[i].1 as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+        i += 1;
+    }
+    hash
+}
+
 /// This macro is useful for third-parties creating new plugins in new rust projects.
 ///
 /// A plugin library uses a naming scheme to make the "give me the plugin" function
 /// visible. Since there is no stable ABI or official way to make a library plugin,
-/// this macro helps plugins be "in sync" with the version of overtone they were
-/// compiled for.
+/// this macro also exports the [`PluginFingerprint`] of the version of overtone
+/// the plugin was compiled for, which the host checks before loading it.
 #[macro_export]
 macro_rules! overtone_plugin {
     ( $e: expr ) => {
+        #[no_mangle]
+        pub static OVERTONE_PLUGIN_FINGERPRINT: $crate::plugin::PluginFingerprint =
+            $crate::plugin::PluginFingerprint::CURRENT;
+
         #[no_mangle]
         pub fn get_overtone_plugin() -> Box<dyn $crate::plugin::Plugin> {
             $e
@@ -181,10 +517,69 @@ pub enum PluginError {
     PluginAlreadyLoaded(),
     /// A plugin, which was referred by this id, does not exist.
     MissingPlugin(String),
+    /// A plugin, which was referred by this id, is not loaded.
+    PluginNotLoaded(String),
+    /// A plugin can't be unloaded while the plugins that require it are loaded.
+    PluginRequired {
+        plugin: String,
+        dependents: Vec<String>,
+    },
+    /// Library couldn't be copied to the temporary file it's opened from
+    /// (which also happens when there's no library at the plugin's path).
+    LibraryNotCopied(std::io::Error),
     /// Library couldn't be loaded for some reason.
     LibraryNotFound(libloading::Error),
+    /// A plugin's key in the project's `[plugins]` table isn't the id its metadata gives.
+    ///
+    /// Both have to match, since plugins are loaded by key but required by id.
+    MismatchedId {
+        key: String,
+        metadata: String,
+    },
     /// Library was loaded but is not recognised as an Overtone plugin.
+    ///
+    /// Plugins built before fingerprints were exported show up as this, too.
     LibraryIsNotOvertonePlugin(),
+    /// Library is an Overtone plugin, but it was built with a different version of overtone
+    /// or of the compiler than the host, so loading it could crash.
+    IncompatibleAbi {
+        host: AbiVersion,
+        plugin: AbiVersion,
+    },
+    /// A plugin requires another plugin that isn't in the project (or isn't loaded yet).
+    MissingDependency {
+        plugin: String,
+        dependency: String,
+    },
+    /// A plugin requires a version of another plugin that the project doesn't have.
+    IncompatibleDependency {
+        plugin: String,
+        dependency: String,
+        required: VersionReq,
+        found: Version,
+    },
+    /// Plugins require each other in a circle, so none of them can load first.
+    ///
+    /// Holds the ids along the circle, starting and ending with the same plugin.
+    DependencyCycle(Vec<String>),
+    /// Two contributions have the same id. `first` is the plugin whose contribution
+    /// was registered first (and stays), `second` is the one being loaded.
+    ContributionCollision {
+        id: String,
+        first: String,
+        second: String,
+    },
+    /// A plugin contributes with a node type whose id is already registered,
+    /// by something other than a plugin (like the built-in nodes).
+    NodeTypeTaken {
+        type_id: String,
+        plugin: String,
+    },
+    /// A plugin contributes with an `Other` kind of contribution that no plugin declared.
+    UnknownContributionKind {
+        plugin: String,
+        kind: String,
+    },
 }
 
 impl From<PluginError> for OvertoneError {
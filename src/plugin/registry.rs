@@ -0,0 +1,322 @@
+//! # Contribution Registry
+//!
+//! Everything the loaded plugins of a project contribute with, indexed so it can be
+//! looked up by id ("the `pcm-wav-exporter`") or by format ("all exporters accepting `audio/pcm`").
+//!
+//! Plugins are trusted to pick unique ids, but the registry checks: a plugin whose
+//! contributions collide with another plugin's (or whose node types collide with built-in ones),
+//! or that contributes an [`Other`] of a kind nobody declared, is turned away before any of
+//! its contributions are added.
+//!
+//! [`Other`]: PluginContribution::Other
+
+#![allow(deprecated)]
+
+use super::{NodeContribution, PluginContribution, PluginContributions, PluginError};
+use crate::renderer::{RenderExporter, Renderer};
+use cables_core::registry::NodeRegistry;
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+/// A contribution, along with the id of the plugin that contributed it.
+pub struct Registered<T> {
+    pub plugin: String,
+    pub contribution: T,
+}
+
+impl<T> Registered<T> {
+    fn new(plugin: &str, contribution: T) -> Self {
+        Self {
+            plugin: plugin.to_string(),
+            contribution,
+        }
+    }
+}
+
+/// A contribution that's not officially recognised by Overtone, of a kind
+/// some plugin declared with [`PluginContribution::ContributionKind`].
+#[derive(Debug, Clone)]
+pub struct OtherContribution {
+    pub kind: String,
+}
+
+/// A kind of contribution some plugin declared.
+struct DeclaredKind {
+    plugin: String,
+    /// Whether the plugin was removed, so the kind is only kept
+    /// for as long as other plugins contribute with it.
+    orphaned: bool,
+}
+
+/// The index of what every loaded plugin contributes with.
+#[derive(Default)]
+pub struct ContributionRegistry {
+    renderers: HashMap<String, Registered<Box<dyn Renderer>>>,
+    exporters: HashMap<String, Registered<Box<dyn RenderExporter>>>,
+    nodes: HashMap<&'static str, Registered<NodeContribution>>,
+    /// The declared contribution kinds, and the plugins that declared them.
+    kinds: HashMap<String, DeclaredKind>,
+    others: HashMap<String, Registered<OtherContribution>>,
+}
+
+impl ContributionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks that everything a plugin contributes with can be added.
+    ///
+    /// A contribution can't collide with one that's already registered (or with another one
+    /// of the same plugin), and a node type can't take the id of one in `node_registry`,
+    /// like a built-in node. An [`PluginContribution::Other`] must be of a kind that was
+    /// declared, by this plugin or another.
+    ///
+    /// What `plugin` itself registered doesn't count, so a new build of a loaded plugin
+    /// can be checked before it takes the old one's place.
+    pub fn check(
+        &self,
+        plugin: &str,
+        contributions: &PluginContributions,
+        node_registry: &NodeRegistry,
+    ) -> Result<(), PluginError> {
+        let mut renderers = Vec::new();
+        let mut exporters = Vec::new();
+        let mut nodes = Vec::new();
+        let mut kinds = Vec::new();
+        let mut others = Vec::new();
+
+        renderers.extend(contributions.renderers.iter().flat_map(|r| r.keys()));
+        exporters.extend(contributions.exporters.iter().flat_map(|e| e.keys()));
+        for contribution in &contributions.contributions {
+            match contribution {
+                PluginContribution::Renderer { id, .. } => renderers.push(id),
+                PluginContribution::Node(node) => nodes.push(node.type_id),
+                PluginContribution::Exporter { id, .. } => exporters.push(id),
+                PluginContribution::ContributionKind(kind) => kinds.push(kind),
+                PluginContribution::Other { id, kind } => others.push((id, kind)),
+            }
+        }
+
+        check_unique(plugin, &self.renderers, renderers.into_iter().map(String::as_str))?;
+        check_unique(plugin, &self.exporters, exporters.into_iter().map(String::as_str))?;
+        check_unique(plugin, &self.nodes, nodes.iter().copied())?;
+        // Node types registered by a plugin are in `self.nodes` too, and were checked above.
+        let built_in =
+            |type_id: &&str| node_registry.contains(type_id) && !self.nodes.contains_key(type_id);
+        if let Some(type_id) = nodes.iter().find(|type_id| built_in(type_id)) {
+            return Err(PluginError::NodeTypeTaken {
+                type_id: type_id.to_string(),
+                plugin: plugin.to_string(),
+            });
+        }
+        check_unique(plugin, &self.others, others.iter().map(|(id, _)| id.as_str()))?;
+        for (index, kind) in kinds.iter().enumerate() {
+            if let Some(first) = self.kinds.get(*kind).filter(|k| k.plugin != plugin) {
+                return Err(collision(kind, &first.plugin, plugin));
+            }
+            if kinds[..index].contains(kind) {
+                return Err(collision(kind, plugin, plugin));
+            }
+        }
+        for (_, kind) in &others {
+            if !self.keeps_kind_without(kind, plugin) && !kinds.contains(kind) {
+                return Err(PluginError::UnknownContributionKind {
+                    plugin: plugin.to_string(),
+                    kind: kind.to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds everything a plugin contributes with.
+    ///
+    /// Nothing is added unless all of it can be (see [`ContributionRegistry::check`]).
+    pub fn add(
+        &mut self,
+        plugin: &str,
+        contributions: PluginContributions,
+        node_registry: &NodeRegistry,
+    ) -> Result<(), PluginError> {
+        self.check(plugin, &contributions, node_registry)?;
+
+        for (id, renderer) in contributions.renderers.into_iter().flatten() {
+            self.renderers.insert(id, Registered::new(plugin, renderer));
+        }
+        for (id, exporter) in contributions.exporters.into_iter().flatten() {
+            self.exporters.insert(id, Registered::new(plugin, exporter));
+        }
+        for contribution in contributions.contributions {
+            match contribution {
+                PluginContribution::Renderer { id, renderer } => {
+                    self.renderers.insert(id, Registered::new(plugin, renderer));
+                }
+                PluginContribution::Node(node) => {
+                    self.nodes.insert(node.type_id, Registered::new(plugin, node));
+                }
+                PluginContribution::Exporter { id, exporter } => {
+                    self.exporters.insert(id, Registered::new(plugin, exporter));
+                }
+                PluginContribution::ContributionKind(kind) => {
+                    self.kinds.insert(
+                        kind,
+                        DeclaredKind {
+                            plugin: plugin.to_string(),
+                            orphaned: false,
+                        },
+                    );
+                }
+                PluginContribution::Other { id, kind } => {
+                    self.others
+                        .insert(id, Registered::new(plugin, OtherContribution { kind }));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Drops everything a plugin contributed with.
+    ///
+    /// The kinds it declared are dropped too, unless other plugins still contribute with them.
+    pub fn remove(&mut self, plugin: &str) {
+        self.renderers.retain(|_, r| r.plugin != plugin);
+        self.exporters.retain(|_, e| e.plugin != plugin);
+        self.nodes.retain(|_, n| n.plugin != plugin);
+        self.others.retain(|_, o| o.plugin != plugin);
+        let others = &self.others;
+        self.kinds.retain(|kind, declared| {
+            if declared.plugin != plugin && !declared.orphaned {
+                return true;
+            }
+            declared.orphaned = true;
+            others.values().any(|o| &o.contribution.kind == kind)
+        });
+    }
+
+    /// Whether a kind would stay declared if `plugin` were removed.
+    fn keeps_kind_without(&self, kind: &str, plugin: &str) -> bool {
+        self.kinds.get(kind).is_some_and(|declared| {
+            (declared.plugin != plugin && !declared.orphaned)
+                || self
+                    .others
+                    .values()
+                    .any(|o| o.plugin != plugin && o.contribution.kind == kind)
+        })
+    }
+
+    pub fn renderer(&self, id: &str) -> Option<&Registered<Box<dyn Renderer>>> {
+        self.renderers.get(id)
+    }
+
+    /// Every renderer, by id.
+    pub fn renderers(&self) -> impl Iterator<Item = (&str, &Registered<Box<dyn Renderer>>)> {
+        self.renderers.iter().map(|(id, r)| (id.as_str(), r))
+    }
+
+    /// The renderers whose results are of the given render format.
+    pub fn renderers_producing<'s>(
+        &'s self,
+        format_id: &'s str,
+    ) -> impl Iterator<Item = (&'s str, &'s Registered<Box<dyn Renderer>>)> {
+        self.renderers()
+            .filter(move |(_, r)| r.contribution.get_render_format_id() == format_id)
+    }
+
+    pub fn exporter(&self, id: &str) -> Option<&Registered<Box<dyn RenderExporter>>> {
+        self.exporters.get(id)
+    }
+
+    /// Every exporter, by id.
+    pub fn exporters(&self) -> impl Iterator<Item = (&str, &Registered<Box<dyn RenderExporter>>)> {
+        self.exporters.iter().map(|(id, e)| (id.as_str(), e))
+    }
+
+    /// The exporters that can export render results of the given format.
+    pub fn exporters_accepting<'s>(
+        &'s self,
+        format_id: &'s str,
+    ) -> impl Iterator<Item = (&'s str, &'s Registered<Box<dyn RenderExporter>>)> {
+        self.exporters().filter(move |(_, e)| {
+            e.contribution
+                .is_render_format_supported(format_id.to_string())
+        })
+    }
+
+    pub fn node(&self, type_id: &str) -> Option<&Registered<NodeContribution>> {
+        self.nodes.get(type_id)
+    }
+
+    /// Every node type contributed by a plugin, by type id.
+    pub fn nodes(&self) -> impl Iterator<Item = (&'static str, &Registered<NodeContribution>)> {
+        self.nodes.iter().map(|(id, n)| (*id, n))
+    }
+
+    /// Whether some plugin declared this kind of contribution.
+    pub fn is_kind_registered(&self, kind: &str) -> bool {
+        self.kinds.contains_key(kind)
+    }
+
+    /// Every declared kind of contribution, with the plugin that declared it.
+    pub fn kinds(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.kinds
+            .iter()
+            .map(|(kind, declared)| (kind.as_str(), declared.plugin.as_str()))
+    }
+
+    pub fn other(&self, id: &str) -> Option<&Registered<OtherContribution>> {
+        self.others.get(id)
+    }
+
+    /// The contributions of a custom kind, by id.
+    pub fn others_of_kind<'s>(
+        &'s self,
+        kind: &'s str,
+    ) -> impl Iterator<Item = (&'s str, &'s Registered<OtherContribution>)> {
+        self.others
+            .iter()
+            .filter(move |(_, o)| o.contribution.kind == kind)
+            .map(|(id, o)| (id.as_str(), o))
+    }
+}
+
+/// Checks that none of the `ids` a plugin contributes are taken (by another plugin), or repeated.
+fn check_unique<'i, K, T>(
+    plugin: &str,
+    registered: &HashMap<K, Registered<T>>,
+    ids: impl Iterator<Item = &'i str>,
+) -> Result<(), PluginError>
+where
+    K: std::borrow::Borrow<str> + std::hash::Hash + Eq,
+{
+    let mut seen = Vec::new();
+    for id in ids {
+        if let Some(first) = registered.get(id).filter(|r| r.plugin != plugin) {
+            return Err(collision(id, &first.plugin, plugin));
+        }
+        if seen.contains(&id) {
+            return Err(collision(id, plugin, plugin));
+        }
+        seen.push(id);
+    }
+    Ok(())
+}
+
+fn collision(id: &str, first: &str, second: &str) -> PluginError {
+    PluginError::ContributionCollision {
+        id: id.to_string(),
+        first: first.to_string(),
+        second: second.to_string(),
+    }
+}
+
+impl Debug for ContributionRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ContributionRegistry")
+            .field("renderers", &self.renderers.keys().collect::<Vec<_>>())
+            .field("exporters", &self.exporters.keys().collect::<Vec<_>>())
+            .field("nodes", &self.nodes.keys().collect::<Vec<_>>())
+            .field("kinds", &self.kinds.keys().collect::<Vec<_>>())
+            .field("others", &self.others.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
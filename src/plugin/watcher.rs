@@ -0,0 +1,117 @@
+//! # Plugin Watcher
+//!
+//! A [`PluginWatcher`] watches the libraries of loaded plugins from a thread of its own,
+//! and tells which plugins were rebuilt.
+//!
+//! Reloading a plugin needs the [`Project`](crate::project::Project), so the watcher only
+//! reports: whoever owns the project takes the ids from [`PluginWatcher::changed`] (say,
+//! once per frame) and reloads those plugins, with
+//! [`Project::reload_plugin`](crate::project::Project::reload_plugin).
+
+use super::{modified_time, LoadedPlugin};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+/// Watches plugin libraries for rebuilds. Stops watching when dropped.
+#[derive(Debug)]
+pub struct PluginWatcher {
+    changed: Receiver<String>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// A library being watched.
+struct Watched {
+    id: String,
+    path: PathBuf,
+    /// The modification time last reported (or that the plugin was loaded with).
+    reported: Option<SystemTime>,
+    /// A modification time seen on the last check, that's reported
+    /// if the file is left alone until the next one.
+    pending: Option<SystemTime>,
+}
+
+impl PluginWatcher {
+    /// Starts watching the libraries of these plugins, checking them every `interval`.
+    ///
+    /// A library is reported once it changed since the plugin was loaded and then stayed
+    /// the same for a whole `interval`, so a library that's still being written isn't
+    /// picked up halfway.
+    pub fn new<'p>(
+        plugins: impl IntoIterator<Item = &'p LoadedPlugin>,
+        interval: Duration,
+    ) -> Self {
+        let mut watched: Vec<_> = plugins
+            .into_iter()
+            .map(|plugin| Watched {
+                id: plugin.id.clone(),
+                path: plugin.path.clone(),
+                reported: plugin.modified,
+                pending: None,
+            })
+            .collect();
+
+        let (sender, changed) = channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stop = stop.clone();
+            thread::spawn(move || {
+                while !stop.load(Ordering::Acquire) {
+                    if check(&mut watched, &sender).is_err() {
+                        // The watcher was dropped.
+                        return;
+                    }
+                    thread::park_timeout(interval);
+                }
+            })
+        };
+
+        Self {
+            changed,
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    /// The ids of the plugins whose library was rebuilt since the last call.
+    pub fn changed(&self) -> Vec<String> {
+        let mut changed: Vec<String> = Vec::new();
+        for id in self.changed.try_iter() {
+            if !changed.contains(&id) {
+                changed.push(id);
+            }
+        }
+        changed
+    }
+}
+
+/// Checks every watched library once, sending the ids of the ones that settled on a new build.
+fn check(watched: &mut [Watched], sender: &Sender<String>) -> Result<(), ()> {
+    for library in watched {
+        let modified = modified_time(&library.path);
+        if modified.is_none() || modified == library.reported {
+            library.pending = None;
+        } else if modified == library.pending {
+            library.reported = modified;
+            library.pending = None;
+            sender.send(library.id.clone()).map_err(|_| ())?;
+        } else {
+            library.pending = modified;
+        }
+    }
+    Ok(())
+}
+
+impl Drop for PluginWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
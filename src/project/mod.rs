@@ -17,7 +17,10 @@
 //! To maintain the invariants of a project intact, a project should be edited through
 //! [`super::editor`].
 
-use crate::plugin::{PluginDependencyEntry, PluginError};
+use crate::plugin::{load_order, PluginDependencyEntry, PluginError, PluginHost, PluginWatcher};
+use crate::task::{self, Task};
+use crate::transformer::ProductionSetup;
+use cables_core::registry::NodeRegistry;
 use std::collections::HashMap;
 pub mod composition;
 pub mod resource;
@@ -29,10 +32,11 @@ use composition::CompositionError;
 use serde_derive::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 /// An Overtone project.
 #[derive(Debug)]
-pub struct Project<'a> {
+pub struct Project {
     /// The contents of the `Overtone.toml` manifest that marks
     /// a folder as an Overtone project.
     pub file: ProjectManifest,
@@ -40,16 +44,23 @@ pub struct Project<'a> {
     /// The path of the folder this project is saved to, if it is saved.
     pub directory: Option<PathBuf>,
 
-    /// The plugins loaded into this project.
-    /// Plugins are lazy-loaded, so this holds
-    /// references to them when they load.
-    pub loaded_plugins: Vec<LoadedPlugin<'a>>,
+    /// The node types production setups can use: the ones built into
+    /// [`cables_core`] and the ones contributed by loaded plugins.
+    pub node_registry: NodeRegistry,
 
     /// The "content" of a project.
     pub content: ProjectContent,
+
+    /// The plugins loaded into this project, and what they contribute with.
+    /// Plugins are lazy-loaded, so this holds
+    /// them when they load.
+    //
+    // This must be declared last, as anything above that came from a plugin
+    // must be dropped before the plugins' libraries close.
+    pub plugin_host: PluginHost,
 }
 
-impl<'a> Info for Project<'a> {
+impl Info for Project {
     fn get_name(&self) -> &str {
         self.file.info.name.as_str()
     }
@@ -64,6 +75,8 @@ pub struct ProjectContent {
 
 const PROJECT_MANIFEST_FILENAME: &str = "Overtone.toml";
 const DEFAULT_COMPOSITIONS_DIRECTORY: &str = "compositions";
+const DEFAULT_PRODUCTIONS_DIRECTORY: &str = "productions";
+const PRODUCTION_EXTENSION: &str = "toml";
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ProjectManifest {
@@ -82,6 +95,7 @@ pub struct ProjectInfo {
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct ConfigurationOverrides {
     compositions_dir: Option<PathBuf>,
+    productions_dir: Option<PathBuf>,
     exports_dir: Option<PathBuf>,
 }
 
@@ -118,7 +132,7 @@ impl ProjectManifest {
     }
 }
 
-impl<'a> Project<'a> {
+impl Project {
     /// Creates a new project with configuration.
     ///
     /// This function takes a [`ProjectManifest`] but don't get confused,
@@ -128,7 +142,8 @@ impl<'a> Project<'a> {
         Self {
             file,
             directory: None,
-            loaded_plugins: Vec::new(),
+            node_registry: NodeRegistry::with_builtin_nodes(),
+            plugin_host: PluginHost::new(),
             content: ProjectContent {
                 compositions: vec![],
             },
@@ -137,15 +152,30 @@ impl<'a> Project<'a> {
 
     /// Loads an overtone project from a directory, if there's a suitable manifest file.
     pub fn load_from_directory<P: AsRef<Path>>(path: P) -> Result<Self, OvertoneError> {
-        let file = ProjectManifest::load_from_directory(&path)?;
-
-        let content = ProjectContent::load_from_directory(&path, &file.configuration_overrides)?;
+        task::block_on(Self::load_from_directory_task(path.as_ref().to_path_buf()))
+    }
 
-        Ok(Project {
-            file,
-            directory: Some(PathBuf::from(path.as_ref())),
-            loaded_plugins: vec![],
-            content,
+    /// Like [`Project::load_from_directory`], but as a task that reports which stage
+    /// of loading it's in, and that can be handed to [`task::spawn`].
+    pub fn load_from_directory_task(
+        path: PathBuf,
+    ) -> impl Task<Output = Result<Self, OvertoneError>> + Send {
+        task::from_fn(move |progress| {
+            progress.set_stage("Reading manifest");
+            let file = ProjectManifest::load_from_directory(&path)?;
+
+            progress.set_stage("Loading compositions");
+            progress.set(0.5);
+            let content =
+                ProjectContent::load_from_directory(&path, &file.configuration_overrides)?;
+
+            Ok(Project {
+                file,
+                directory: Some(path),
+                node_registry: NodeRegistry::with_builtin_nodes(),
+                plugin_host: PluginHost::new(),
+                content,
+            })
         })
     }
 
@@ -184,6 +214,7 @@ impl<'a> Project<'a> {
         std::fs::create_dir(&path).map_err(IOError::Generic)?;
         std::fs::create_dir(&path.join("assets")).map_err(IOError::Generic)?;
         std::fs::create_dir(&path.join("compositions")).map_err(IOError::Generic)?;
+        std::fs::create_dir(path.join("productions")).map_err(IOError::Generic)?;
         std::fs::create_dir(&path.join("exports")).map_err(IOError::Generic)?;
 
         Ok(())
@@ -195,33 +226,314 @@ impl<'a> Project<'a> {
     }
 
     /// Returns an iterators through the loaded plugins. Might be useful.
-    pub fn iter_loaded_plugins(&'a self) -> std::slice::Iter<'a, LoadedPlugin<'a>> {
-        self.loaded_plugins.iter()
+    pub fn iter_loaded_plugins(&self) -> std::slice::Iter<'_, LoadedPlugin> {
+        self.plugin_host.plugins().iter()
     }
 
     /// Loads a plugin given its id. The plugin in question must have been "installed," that is,
     /// have a dependency entry in the project containing the path of the shared library.
     ///
     /// This function also conveniently returns a reference to the [`LoadedPlugin`].
-    pub fn load_plugin(
-        &'a mut self,
+    pub fn load_plugin(&mut self, plugin_id: String) -> Result<&LoadedPlugin, PluginError> {
+        task::block_on(self.load_plugin_task(plugin_id))
+    }
+
+    /// Like [`Project::load_plugin`], but as a task that reports which stage of loading it's in.
+    ///
+    /// The plugins this one requires must have been loaded already.
+    pub fn load_plugin_task(
+        &mut self,
         plugin_id: String,
-    ) -> Result<&'a LoadedPlugin<'a>, PluginError> {
-        if self.loaded_plugins.iter().any(|p| p.id == plugin_id) {
-            return Err(PluginError::PluginAlreadyLoaded());
+    ) -> impl Task<Output = Result<&LoadedPlugin, PluginError>> + '_ {
+        task::from_fn(move |progress| {
+            if self.plugin_host.is_loaded(&plugin_id) {
+                return Err(PluginError::PluginAlreadyLoaded());
+            }
+
+            let entry = self
+                .file
+                .plugins
+                .get(&plugin_id)
+                .ok_or_else(|| PluginError::MissingPlugin(plugin_id.clone()))?;
+
+            progress.set_stage("Opening library");
+            let loaded = LoadedPlugin::load_from_dependency_entry(&self.directory, &plugin_id, entry)?;
+
+            progress.set_stage("Checking dependencies");
+            let metadata = loaded.get_plugin().get_metadata();
+            let loaded_metadata = self.plugin_host.metadata();
+            for requirement in &metadata.requires {
+                requirement.check_loaded(&metadata.id, &loaded_metadata)?;
+            }
+
+            progress.set_stage("Registering contributions");
+            progress.set(0.5);
+            self.add_loaded_plugin(loaded, false)
+        })
+    }
+
+    /// Loads every plugin in the project's `[plugins]` table that isn't loaded yet,
+    /// each one after the plugins it requires.
+    ///
+    /// If a plugin requires one that's missing, in a version the project doesn't have,
+    /// or that (eventually) requires it back, nothing is loaded.
+    pub fn load_plugins(&mut self) -> Result<&[LoadedPlugin], PluginError> {
+        task::block_on(self.load_plugins_task())
+    }
+
+    /// Like [`Project::load_plugins`], but as a task that reports how many plugins are done.
+    pub fn load_plugins_task(
+        &mut self,
+    ) -> impl Task<Output = Result<&[LoadedPlugin], PluginError>> + '_ {
+        task::from_fn(move |progress| {
+            let mut entries: Vec<_> = self
+                .file
+                .plugins
+                .iter()
+                .filter(|(id, _)| !self.plugin_host.is_loaded(id))
+                .collect();
+            entries.sort_by_key(|(id, _)| *id);
+
+            progress.set_stage("Opening libraries");
+            let mut pending = entries
+                .into_iter()
+                .map(|(id, entry)| {
+                    LoadedPlugin::load_from_dependency_entry(&self.directory, id, entry).map(Some)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            progress.set_stage("Resolving dependencies");
+            let pending_metadata: Vec<_> = pending
+                .iter()
+                .flatten()
+                .map(|p| p.get_plugin().get_metadata())
+                .collect();
+            let order = load_order(&pending_metadata, &self.plugin_host.metadata())?;
+
+            progress.set_stage("Registering contributions");
+            let count = order.len();
+            for (done, index) in order.into_iter().enumerate() {
+                self.add_loaded_plugin(pending[index].take().unwrap(), false)?;
+                progress.set((done + 1) as f32 / count as f32);
+            }
+
+            Ok(self.plugin_host.plugins())
+        })
+    }
+
+    /// Returns the loaded plugin with this id, if it's loaded.
+    pub fn get_loaded_plugin(&self, plugin_id: &str) -> Option<&LoadedPlugin> {
+        self.plugin_host.get(plugin_id)
+    }
+
+    /// Unloads a plugin: its node types are unregistered and every contribution it made
+    /// is dropped before its library is closed.
+    ///
+    /// A plugin can't be unloaded while plugins that require it are loaded.
+    ///
+    /// # Safety
+    ///
+    /// Nothing built from the plugin's contributions may be alive, as its code lives in the
+    /// library that's closed: production setups using the plugin's nodes (and their pipelines),
+    /// and anything the plugin's renderers or exporters handed out, must have been dropped.
+    pub unsafe fn unload_plugin(&mut self, plugin_id: &str) -> Result<(), PluginError> {
+        let dependents = self.plugin_host.dependents(plugin_id);
+        if !dependents.is_empty() {
+            return Err(PluginError::PluginRequired {
+                plugin: plugin_id.to_string(),
+                dependents,
+            });
         }
+        self.remove_loaded_plugin(plugin_id)
+    }
 
+    /// Loads a plugin again from its library, which is useful after rebuilding it.
+    /// The plugin's [`Plugin::on_plugin_load`] runs again.
+    ///
+    /// The new build is opened and checked while the old one is still loaded: it must still be
+    /// a version the plugins requiring it work with, and its contributions mustn't collide with
+    /// anything but the old build's. Only then is the old build unloaded, so if anything fails,
+    /// the old build stays as it was.
+    ///
+    /// # Safety
+    ///
+    /// The old build's library is closed, so the same as for [`Project::unload_plugin`] applies.
+    ///
+    /// [`Plugin::on_plugin_load`]: crate::plugin::Plugin::on_plugin_load
+    pub unsafe fn reload_plugin(&mut self, plugin_id: &str) -> Result<&LoadedPlugin, PluginError> {
+        if !self.plugin_host.is_loaded(plugin_id) {
+            return Err(PluginError::PluginNotLoaded(plugin_id.to_string()));
+        }
         let entry = self
             .file
             .plugins
-            .iter()
-            .find(|p| p.0.as_str().eq(&plugin_id))
-            .ok_or_else(|| PluginError::MissingPlugin(plugin_id.clone()))?;
+            .get(plugin_id)
+            .ok_or_else(|| PluginError::MissingPlugin(plugin_id.to_string()))?;
+        let loaded = LoadedPlugin::load_from_dependency_entry(&self.directory, plugin_id, entry)?;
+
+        let metadata = loaded.get_plugin().get_metadata();
+        let mut loaded_metadata = self.plugin_host.metadata();
+        loaded_metadata.retain(|m| m.id != plugin_id);
+        for requirement in &metadata.requires {
+            requirement.check_loaded(&metadata.id, &loaded_metadata)?;
+        }
+        loaded_metadata.push(metadata);
+        for dependent in &loaded_metadata {
+            for requirement in dependent.requires.iter().filter(|r| r.id == plugin_id) {
+                requirement.check_loaded(&dependent.id, &loaded_metadata)?;
+            }
+        }
 
-        let loaded = LoadedPlugin::load_from_dependency_entry(&self.directory, entry.0, entry.1)?;
+        self.add_loaded_plugin(loaded, true)
+    }
+
+    /// Reloads every plugin whose library changed on disk since it was loaded,
+    /// and returns their ids.
+    ///
+    /// Call this every now and then (say, when the window regains focus) to pick up plugins
+    /// as they're rebuilt, or use a [`PluginWatcher`] to know when there's something to reload.
+    ///
+    /// # Safety
+    ///
+    /// The same as for [`Project::unload_plugin`] applies to every plugin that changed.
+    ///
+    /// [`PluginWatcher`]: crate::plugin::PluginWatcher
+    pub unsafe fn reload_changed_plugins(&mut self) -> Result<Vec<String>, PluginError> {
+        let changed = self.plugin_host.changed_on_disk();
+        for plugin_id in &changed {
+            self.reload_plugin(plugin_id)?;
+        }
+        Ok(changed)
+    }
+
+    /// Starts watching the libraries of the loaded plugins from another thread, checking them
+    /// every `interval`. Reload the plugins it reports with [`Project::reload_plugin`].
+    pub fn watch_plugins(&self, interval: Duration) -> PluginWatcher {
+        self.plugin_host.watch(interval)
+    }
+
+    /// Unregisters a plugin's node types, then removes it from the [`PluginHost`].
+    ///
+    /// # Safety
+    ///
+    /// See [`PluginHost::remove`].
+    unsafe fn remove_loaded_plugin(&mut self, plugin_id: &str) -> Result<(), PluginError> {
+        for (type_id, node) in self.plugin_host.contributions().nodes() {
+            if node.plugin == plugin_id {
+                self.node_registry.unregister(type_id);
+            }
+        }
+        self.plugin_host.remove(plugin_id)
+    }
+
+    /// Lets a freshly loaded plugin know it's loading, then hands it to the
+    /// [`PluginHost`] and registers the node types it contributes with.
+    ///
+    /// Its contributions are checked first, so a plugin that's turned away
+    /// never runs its [`Plugin::on_plugin_load`] against the project.
+    ///
+    /// With `replace`, the plugin is a new build of a loaded one, which is
+    /// unloaded once the new build's contributions check out.
+    ///
+    /// [`Plugin::on_plugin_load`]: crate::plugin::Plugin::on_plugin_load
+    fn add_loaded_plugin(
+        &mut self,
+        mut loaded: LoadedPlugin,
+        replace: bool,
+    ) -> Result<&LoadedPlugin, PluginError> {
+        let contributions = loaded.get_plugin().get_contributions();
+        let checked = if replace {
+            self.plugin_host
+                .check_replacement(&loaded, &contributions, &self.node_registry)
+        } else {
+            self.plugin_host
+                .check(&loaded, &contributions, &self.node_registry)
+        };
+        if let Err(error) = checked {
+            // The contributions' code lives in the plugin's library.
+            drop(contributions);
+            return Err(error);
+        }
+        if replace {
+            // SAFETY: only `reload_plugin` replaces plugins, and its caller vouches for the old build.
+            unsafe { self.remove_loaded_plugin(&loaded.id)? };
+        }
+
+        loaded.plugin.on_plugin_load(self);
+
+        let id = loaded.id.clone();
+        self.plugin_host
+            .insert(loaded, contributions, &self.node_registry)?;
+        for (_, node) in self.plugin_host.contributions().nodes() {
+            if node.plugin == id {
+                node.contribution.register(&mut self.node_registry);
+            }
+        }
+        Ok(self.plugin_host.get(&id).unwrap())
+    }
+}
+
+// MARK: Productions
+
+impl Project {
+    /// The directory the project's production setups are saved in.
+    ///
+    /// Returns `None` if the project was never saved.
+    pub fn productions_directory(&self) -> Option<PathBuf> {
+        let directory = self.directory.as_ref()?;
+        let productions = self
+            .file
+            .configuration_overrides
+            .productions_dir
+            .as_deref()
+            .unwrap_or(Path::new(DEFAULT_PRODUCTIONS_DIRECTORY));
+        Some(directory.join(productions))
+    }
+
+    /// Lists the names of the project's production setups, in alphabetical order.
+    pub fn production_names(&self) -> Result<Vec<String>, OvertoneError> {
+        let directory = self
+            .productions_directory()
+            .ok_or(ProjectError::ProjectNotSaved)?;
+        if !directory.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut names: Vec<String> = fs::read_dir(&directory)
+            .map_err(IOError::Generic)?
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                if path.extension()? != PRODUCTION_EXTENSION {
+                    return None;
+                }
+                path.file_stem()?.to_str().map(str::to_string)
+            })
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    /// Loads one of the project's production setups.
+    ///
+    /// Plugins contributing the setup's nodes must be loaded first.
+    pub fn load_production(&self, name: &str) -> Result<ProductionSetup, OvertoneError> {
+        let directory = self
+            .productions_directory()
+            .ok_or(ProjectError::ProjectNotSaved)?;
+        let path = directory.join(name).with_extension(PRODUCTION_EXTENSION);
+        Ok(ProductionSetup::load_from_file(path, &self.node_registry)?)
+    }
 
-        self.loaded_plugins.push(loaded);
-        Ok(self.loaded_plugins.last().unwrap())
+    /// Saves a production setup into the project, as `productions/<name>.toml`.
+    pub fn save_production(&self, production: &ProductionSetup) -> Result<(), OvertoneError> {
+        let directory = self
+            .productions_directory()
+            .ok_or(ProjectError::ProjectNotSaved)?;
+        fs::create_dir_all(&directory).map_err(IOError::Generic)?;
+        let path = directory
+            .join(production.name.as_str())
+            .with_extension(PRODUCTION_EXTENSION);
+        Ok(production.save_to_file(path, &self.node_registry)?)
     }
 }
 
@@ -282,6 +594,8 @@ fn load_project_compositions<P: AsRef<Path>>(
 pub enum ProjectError {
     SaveLocationAlreadyExists,
     SaveLocationNotADirectory,
+    /// The project has no directory yet, so it has nowhere to keep its files.
+    ProjectNotSaved,
 }
 
 impl From<ProjectError> for OvertoneError {
@@ -18,6 +18,7 @@
 #![allow(dead_code)]
 
 use crate::project::composition::Composition;
+use crate::task::BoxTask;
 use std::path::PathBuf;
 
 /// Trait for anything that can render an composition to a [`RenderResult`].
@@ -25,8 +26,11 @@ use std::path::PathBuf;
 pub trait Renderer {
     /// Renders the given elements using resources from the [`Composition`].
     ///
-    /// TODO: Make this method an asynchronously running task that can be probed and cancelled.
-    fn render(&self, composition: &Composition /* fragment slice */) -> Box<dyn RenderResult>;
+    /// The render runs as a task, so it can be probed and cancelled.
+    fn render<'a>(
+        &'a self,
+        composition: &'a Composition, /* fragment slice */
+    ) -> BoxTask<'a, Box<dyn RenderResult>>;
 
     /// Returns an identifier used by previewers and exporters to identify
     /// the type hidden behind the opaque `dyn RenderResult`.
@@ -75,7 +79,9 @@ pub trait RenderExporter {
     fn is_render_format_supported(&self, format_id: String) -> bool;
 
     /// Exports a render result to a location.
-    fn export(&self) -> Result<(), ExportError>;
+    ///
+    /// The export runs as a task, so it can be probed and cancelled.
+    fn export(&self) -> BoxTask<'_, Result<(), ExportError>>;
 }
 
 #[derive(Debug)]
@@ -88,6 +94,8 @@ pub enum ExportError {
     IOError(std::io::Error),
     /// No location chosen
     NoTargetLocationChosen,
+    /// The export was cancelled before it finished.
+    Cancelled,
     /// Too lazy to implement a proper error right now during this
     /// stage of iterative development. You know how it is.
     ///
@@ -4,10 +4,29 @@
 //! which may resolve in the future.
 //!
 //! `Task`, however, can give information about its progress while running.
+//!
+//! ## Running Tasks
+//!
+//! [`block_on`] runs a task on the current thread, and [`spawn`] runs it on a thread of
+//! its own, handing back a [`TaskHandle`] that can be watched, cancelled, joined or
+//! polled as a task itself.
+//!
+//! ```
+//! # use overtone::task::{self, Task};
+//! let handle = task::spawn(task::from_fn(|progress| {
+//!     progress.set_stage("Counting");
+//!     (0..10).sum::<u32>()
+//! }));
+//! assert_eq!(handle.join(), 45);
+//! ```
 
 use std::future::Future;
-use std::pin::Pin;
-use std::task::{Context};
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::pin::{pin, Pin};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Wake, Waker};
+use std::thread::{JoinHandle, Thread};
 
 /// The main `Task` trait.
 pub trait Task {
@@ -15,6 +34,37 @@ pub trait Task {
     type Output;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output>;
+
+    /// How far along the task is, if it keeps track of that.
+    ///
+    /// Clone the handle to watch (or cancel) the task from elsewhere.
+    fn progress(&self) -> Option<&Progress> {
+        None
+    }
+
+    /// The token that stops the task, if it can be stopped.
+    fn cancellation(&self) -> Option<&CancellationToken> {
+        self.progress().map(Progress::cancellation)
+    }
+}
+
+/// A task that lives behind a pointer, such as the ones renderers and exporters return.
+pub type BoxTask<'a, T> = Pin<Box<dyn Task<Output = T> + 'a>>;
+
+impl<T: Task + ?Sized> Task for Pin<Box<T>> {
+    type Output = T::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.get_mut().as_mut().poll(cx)
+    }
+
+    fn progress(&self) -> Option<&Progress> {
+        self.as_ref().get_ref().progress()
+    }
+
+    fn cancellation(&self) -> Option<&CancellationToken> {
+        self.as_ref().get_ref().cancellation()
+    }
 }
 
 pub enum Poll<T> {
@@ -40,4 +90,244 @@ impl<Fut> Task for FutureTask<Fut> where Fut: Future {
             std::task::Poll::Pending => {Poll::Pending}
         }
     }
-}
\ No newline at end of file
+}
+
+/// A task that does all of its work in a closure, the first time it's polled.
+///
+/// The closure gets the task's [`Progress`] to report on (and check for cancellation).
+pub struct FnTask<F> {
+    work: Option<F>,
+    progress: Progress,
+}
+
+/// Creates a [`FnTask`] out of some blocking work.
+///
+/// Pair it with [`spawn`] to move the work off the current thread.
+pub fn from_fn<F, T>(work: F) -> FnTask<F>
+where
+    F: FnOnce(&Progress) -> T,
+{
+    FnTask {
+        work: Some(work),
+        progress: Progress::new(),
+    }
+}
+
+impl<F> Unpin for FnTask<F> {}
+
+impl<F, T> Task for FnTask<F>
+where
+    F: FnOnce(&Progress) -> T,
+{
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let work = this.work.take().expect("`FnTask` polled after completion.");
+        let output = work(&this.progress);
+        this.progress.set(1.0);
+        Poll::Ready(output)
+    }
+
+    fn progress(&self) -> Option<&Progress> {
+        Some(&self.progress)
+    }
+}
+
+/// A flag that asks some work to stop.
+///
+/// Cloning a `CancellationToken` gives another handle to the same flag.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks the work to stop. It's up to the work to notice, with [`CancellationToken::is_cancelled`].
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// How far along some work is, shared between the work and whoever watches it.
+///
+/// Cloning a `Progress` gives another handle to the same progress.
+#[derive(Clone, Debug, Default)]
+pub struct Progress {
+    shared: Arc<ProgressState>,
+    cancellation: CancellationToken,
+}
+
+#[derive(Debug, Default)]
+struct ProgressState {
+    /// The bits of an `f32` between `0.0` and `1.0`.
+    fraction: AtomicU32,
+    /// What the work is busy with, to show next to the fraction.
+    stage: Mutex<Option<String>>,
+}
+
+impl Progress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How much of the work is done, from `0.0` to `1.0`.
+    pub fn get(&self) -> f32 {
+        f32::from_bits(self.shared.fraction.load(Ordering::Relaxed))
+    }
+
+    pub fn set(&self, fraction: f32) {
+        let fraction = fraction.clamp(0.0, 1.0);
+        self.shared.fraction.store(fraction.to_bits(), Ordering::Relaxed);
+    }
+
+    /// What the work is busy with, like "Loading compositions", if it said.
+    pub fn stage(&self) -> Option<String> {
+        self.shared.stage.lock().unwrap().clone()
+    }
+
+    pub fn set_stage(&self, stage: impl Into<String>) {
+        *self.shared.stage.lock().unwrap() = Some(stage.into());
+    }
+
+    /// The token that cancels this work.
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    /// Asks the work to stop. It's up to the work to notice, with [`Progress::is_cancelled`].
+    pub fn cancel(&self) {
+        self.cancellation.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.is_cancelled()
+    }
+}
+
+/// Runs a task to completion on the current thread, which sleeps while the task waits.
+pub fn block_on<T: Task>(task: T) -> T::Output {
+    struct ThreadWaker(Thread);
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut task = pin!(task);
+    loop {
+        match task.as_mut().poll(&mut cx) {
+            Poll::Ready(value) => return value,
+            Poll::Pending => std::thread::park(),
+        }
+    }
+}
+
+/// Runs a task to completion on a thread of its own.
+///
+/// The handle shares the task's [`Progress`], so the task can be watched and cancelled
+/// while it runs.
+pub fn spawn<T>(task: T) -> TaskHandle<T::Output>
+where
+    T: Task + Send + 'static,
+    T::Output: Send + 'static,
+{
+    let progress = task.progress().cloned();
+    let shared = Arc::new(Mutex::new(Spawned {
+        output: None,
+        waker: None,
+    }));
+
+    let thread = {
+        let shared = shared.clone();
+        std::thread::spawn(move || {
+            let output = catch_unwind(AssertUnwindSafe(|| block_on(task)));
+            let mut shared = shared.lock().unwrap();
+            shared.output = Some(output);
+            if let Some(waker) = shared.waker.take() {
+                waker.wake();
+            }
+        })
+    };
+
+    TaskHandle {
+        progress,
+        shared,
+        thread: Some(thread),
+    }
+}
+
+/// A task running on another thread, started with [`spawn`].
+///
+/// The handle is a task itself, which resolves to the spawned task's output.
+/// If the spawned task panics, so does whoever joins or polls the handle.
+pub struct TaskHandle<T> {
+    progress: Option<Progress>,
+    shared: Arc<Mutex<Spawned<T>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+struct Spawned<T> {
+    output: Option<std::thread::Result<T>>,
+    waker: Option<Waker>,
+}
+
+impl<T> TaskHandle<T> {
+    /// Whether the task is done and its output can be taken without waiting.
+    pub fn is_finished(&self) -> bool {
+        self.shared.lock().unwrap().output.is_some()
+    }
+
+    /// Asks the task to stop, if it can be stopped.
+    pub fn cancel(&self) {
+        if let Some(progress) = &self.progress {
+            progress.cancel();
+        }
+    }
+
+    /// Waits for the task to finish and returns its output.
+    pub fn join(self) -> T {
+        block_on(self)
+    }
+}
+
+impl<T> Unpin for TaskHandle<T> {}
+
+impl<T> Task for TaskHandle<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let mut shared = this.shared.lock().unwrap();
+        match shared.output.take() {
+            Some(output) => {
+                drop(shared);
+                if let Some(thread) = this.thread.take() {
+                    let _ = thread.join();
+                }
+                match output {
+                    Ok(value) => Poll::Ready(value),
+                    Err(panic) => resume_unwind(panic),
+                }
+            }
+            None => {
+                shared.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+
+    fn progress(&self) -> Option<&Progress> {
+        self.progress.as_ref()
+    }
+}
@@ -0,0 +1,544 @@
+//! # Pull Nodes in Cables Graphs
+//!
+//! Production setups run on a [`cables_core`] graph, which pushes values from node to node
+//! once per run. A [`PullNode`] lets a pull-model [`Node`] take part in one anyway:
+//! every run, it pulls one chunk from each of the node's outputs and writes it into the
+//! graph, and the node reads its inputs from the graph as if they were [`Source`]s.
+//!
+//! A [`Sink`](super::Sink) pulls in a loop until its streams end, so it can't drain
+//! within a single run. It drains on its own thread instead, which receives one chunk
+//! per input every time the pipeline runs.
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::JoinHandle;
+use cables_core::graph::{ProcessContext, SocketData};
+use crate::task::Progress;
+use super::{ExportError, Node, NodeRef, SocketConnectionError, SocketIdx, Source, StreamInfo};
+
+/// How many runs' worth of chunks a sink can hold before the pipeline waits for it to catch up.
+const SINK_BUFFER_BLOCKS: usize = 4;
+
+/// What a [`PullNode`]'s socket carries in a cables graph: one chunk of a stream.
+#[derive(Clone, Debug)]
+pub struct Chunk<T> {
+    pub info: StreamInfo,
+    /// The chunk itself, or `None` once the stream has ended.
+    pub item: Option<T>,
+}
+
+impl<T> Default for Chunk<T> {
+    fn default() -> Self {
+        Self {
+            info: StreamInfo::default(),
+            item: None,
+        }
+    }
+}
+
+/// A pull-model [`Node`], wrapped so it can be inserted into a [`cables_core::graph::Graph`].
+///
+/// The pull model doesn't know the types of its sockets until they're connected,
+/// so they have to be declared, in order, with [`PullNode::input`] and [`PullNode::output`].
+/// In the graph, a socket declared with `T` carries a [`Chunk<T>`].
+///
+/// ```no_run
+/// # use overtone::transformer::{NodeRef, PullNode};
+/// # fn gain_node() -> NodeRef { unimplemented!() }
+/// let gain = PullNode::new(gain_node())
+///     .input::<Vec<f32>>("in")
+///     .output::<Vec<f32>>("out");
+/// ```
+pub struct PullNode {
+    node: NodeRef,
+    inputs: Vec<InputSocket>,
+    outputs: Vec<OutputSocket>,
+    /// Only set if the node is a sink.
+    drain: Option<Arc<DrainState>>,
+}
+
+struct InputSocket {
+    name: &'static str,
+    data: fn() -> SocketData,
+    /// Makes the node the pull node is connected to, which reads from the graph.
+    feed: fn(Slot) -> NodeRef,
+    /// Makes a function that takes what the graph wrote to a sink's input, to send to the sink.
+    forward: fn(NonNull<u8>) -> Forward,
+}
+
+struct OutputSocket {
+    name: &'static str,
+    data: fn() -> SocketData,
+    /// Turns the source the node gave for this socket into a function that
+    /// pulls from it and writes into the graph.
+    write: MakeWriter,
+}
+
+type Writer = Box<dyn FnMut() + Send>;
+type MakeWriter = fn(Box<dyn Any>, NonNull<u8>) -> Result<Writer, SocketConnectionError>;
+/// Returns a copy of the chunk the graph wrote to an input, and whether its stream has ended.
+type Forward = Box<dyn FnMut() -> (AnyChunk, bool) + Send>;
+/// A [`Chunk`] of any type.
+type AnyChunk = Box<dyn Any + Send>;
+
+/// How a sink's drain is going.
+#[derive(Default)]
+struct DrainState {
+    thread: Mutex<Option<JoinHandle<Result<(), ExportError>>>>,
+    /// Whether every input of the sink has reached the end of its stream.
+    ended: AtomicBool,
+    /// The progress of the drain, which starts over whenever a new drain starts.
+    progress: Mutex<Progress>,
+    /// Where the sink's inputs receive their chunks from.
+    inbox: Arc<Inbox>,
+}
+
+impl PullNode {
+    pub fn new(node: NodeRef) -> Self {
+        let is_sink = node.write().unwrap().as_sink().is_some();
+        Self {
+            node,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            drain: is_sink.then(Default::default),
+        }
+    }
+
+    /// Declares the node's next input socket, which pulls `T`s.
+    pub fn input<T: Clone + Send + Sync + 'static>(mut self, name: &'static str) -> Self {
+        fn forward<T: Clone + Send + Sync + 'static>(pointer: NonNull<u8>) -> Forward {
+            let chunk = cables_core::as_input::<Chunk<T>>(pointer);
+            Box::new(move || (Box::new(chunk.clone()), chunk.item.is_none()))
+        }
+
+        // A sink's inputs are connected once and for all, since the sink holds on to its
+        // node while it drains. Every pipeline the sink is bound to sends to the same inbox.
+        if let Some(drain) = &self.drain {
+            let feed: NodeRef = Arc::new(RwLock::new(ChannelFeed::<T> {
+                inbox: drain.inbox.clone(),
+                input: self.inputs.len(),
+                marker: PhantomData,
+            }));
+            self.node
+                .write()
+                .unwrap()
+                .connect(self.inputs.len(), feed, 0)
+                .unwrap_or_else(|error| panic!("pull node refused input `{name}`: {error:?}"));
+        }
+
+        self.inputs.push(InputSocket {
+            name,
+            data: SocketData::input::<Chunk<T>>,
+            feed: |slot| Arc::new(RwLock::new(Feed::<T> { slot, marker: PhantomData })),
+            forward: forward::<T>,
+        });
+        self
+    }
+
+    /// Declares the node's next output socket, which is a source of `T`s.
+    pub fn output<T: Send + 'static>(mut self, name: &'static str) -> Self {
+        fn write<T: Send + 'static>(source: Box<dyn Any>, pointer: NonNull<u8>) -> Result<Writer, SocketConnectionError> {
+            let mut source = *source
+                .downcast::<Box<dyn Source<Item = T>>>()
+                .map_err(|_| SocketConnectionError::IncorrectFormat)?;
+            let out = cables_core::as_output::<Chunk<T>>(pointer);
+            let mut ended = false;
+            Ok(Box::new(move || {
+                out.info = source.info();
+                out.item = if ended { None } else { source.pull() };
+                ended = out.item.is_none();
+            }))
+        }
+
+        self.outputs.push(OutputSocket {
+            name,
+            data: SocketData::new::<Chunk<T>>,
+            write: write::<T>,
+        });
+        self
+    }
+
+    /// The node this wraps.
+    pub fn node(&self) -> &NodeRef {
+        &self.node
+    }
+
+    pub fn is_sink(&self) -> bool {
+        self.drain.is_some()
+    }
+
+    /// Whether every input of this sink has reached the end of its stream,
+    /// so running the pipeline again won't give it anything new.
+    ///
+    /// Nodes that aren't sinks never end.
+    pub fn has_ended(&self) -> bool {
+        self.drain
+            .as_ref()
+            .is_some_and(|drain| drain.ended.load(Ordering::Acquire))
+    }
+
+    /// Whether this sink's drain is over (it may have failed), and is waiting
+    /// to be collected with [`PullNode::wait_for_drain`].
+    pub fn has_finished_draining(&self) -> bool {
+        self.drain.as_ref().is_some_and(|drain| {
+            drain
+                .thread
+                .lock()
+                .unwrap()
+                .as_ref()
+                .is_some_and(JoinHandle::is_finished)
+        })
+    }
+
+    /// How far along this sink is in draining, if it is a sink.
+    pub fn progress(&self) -> Option<Progress> {
+        self.drain
+            .as_ref()
+            .map(|drain| drain.progress.lock().unwrap().clone())
+    }
+
+    /// Waits for this sink to finish draining and returns how that went.
+    ///
+    /// The sink finishes once its streams end, or once the pipeline it is part of
+    /// is dropped. If it never started draining, or isn't a sink, this returns `Ok(())`.
+    pub fn wait_for_drain(&self) -> Result<(), ExportError> {
+        let Some(thread) = self.drain.as_ref().and_then(|drain| drain.thread.lock().unwrap().take()) else {
+            return Ok(());
+        };
+        thread
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    }
+
+    /// Binds a sink, which doesn't lock its node: a drain from an earlier pipeline may be
+    /// holding on to it, and goes on with what this pipeline sends once the earlier one is dropped.
+    fn bind_sink<'pip>(&self, parameters: &mut dyn Iterator<Item = NonNull<u8>>, drain: Arc<DrainState>) -> Box<dyn FnMut(&ProcessContext) + Send + 'pip> {
+        let mut forwards: Vec<Forward> = self
+            .inputs
+            .iter()
+            .map(|input| (input.forward)(parameters.next().unwrap()))
+            .collect();
+        let (generation, sender) = drain.inbox.open();
+        let mut sender = Some(sender);
+        // Whether this binding started a drain, or found one running to send to.
+        let mut attached = false;
+
+        let node = self.node.clone();
+        drain.ended.store(false, Ordering::Release);
+
+        Box::new(move |_| {
+            let Some(channel) = &sender else {
+                return;
+            };
+
+            let mut thread = drain.thread.lock().unwrap();
+            // A drain that's over isn't started again, even once it's collected.
+            if !std::mem::replace(&mut attached, true) && thread.is_none() {
+                drain.inbox.restart(generation);
+                let node = node.clone();
+                let progress = Progress::new();
+                *drain.progress.lock().unwrap() = progress.clone();
+                let hang_up = HangUp(drain.inbox.clone());
+                *thread = Some(std::thread::spawn(move || {
+                    let _hang_up = hang_up;
+                    let mut node = node.write().unwrap();
+                    node.as_sink().map_or(Ok(()), |sink| sink.drain(&progress))
+                }));
+            }
+            drop(thread);
+
+            let mut ended = true;
+            let block = forwards
+                .iter_mut()
+                .map(|forward| {
+                    let (chunk, end) = forward();
+                    ended &= end;
+                    chunk
+                })
+                .collect();
+            if channel.send(block).is_err() {
+                // The drain is over (it failed, or stopped pulling early), and nobody's left to send to.
+                sender = None;
+                return;
+            }
+            if ended {
+                // Hang up, so the sink sees its streams end even if it pulls again.
+                sender = None;
+                drain.ended.store(true, Ordering::Release);
+            }
+        })
+    }
+}
+
+impl cables_core::graph::Node for PullNode {
+    fn bind_parameters<'pip>(&self, parameters: &mut dyn Iterator<Item = NonNull<u8>>) -> Box<dyn FnMut(&ProcessContext) + Send + 'pip> {
+        if let Some(drain) = &self.drain {
+            return self.bind_sink(parameters, drain.clone());
+        }
+
+        let mut node = self.node.write().unwrap();
+
+        let slots: Vec<_> = self
+            .inputs
+            .iter()
+            .enumerate()
+            .map(|(index, input)| {
+                let slot = Arc::new(RwLock::new(Some(SlotPointer(parameters.next().unwrap()))));
+                node.connect(index, (input.feed)(slot.clone()), 0)
+                    .unwrap_or_else(|error| panic!("pull node refused input `{}`: {error:?}", input.name));
+                slot
+            })
+            .collect();
+
+        let mut writers: Vec<Writer> = self
+            .outputs
+            .iter()
+            .enumerate()
+            .map(|(index, output)| {
+                let source = node.as_source(index).and_then(|source| (output.write)(source, parameters.next().unwrap()));
+                source.unwrap_or_else(|error| panic!("pull node has no source for output `{}`: {error:?}", output.name))
+            })
+            .collect();
+
+        let slots = Slots(slots);
+
+        Box::new(move |_| {
+            let _ = &slots;
+            for writer in writers.iter_mut() {
+                writer();
+            }
+        })
+    }
+
+    fn input_socket(&self, socket_index: usize) -> Option<SocketData> {
+        self.inputs.get(socket_index).map(|input| (input.data)().named(input.name))
+    }
+
+    fn output_socket(&self, socket_index: usize) -> Option<SocketData> {
+        self.outputs.get(socket_index).map(|output| (output.data)().named(output.name))
+    }
+}
+
+/// Where a bound [`PullNode`]'s input reads from, in the pipeline's storage.
+type Slot = Arc<RwLock<Option<SlotPointer>>>;
+
+struct SlotPointer(NonNull<u8>);
+
+// SAFETY: The pointer is only read through while the slot's lock is held,
+// and the pipeline clears it (taking the lock) before its storage goes away.
+unsafe impl Send for SlotPointer {}
+unsafe impl Sync for SlotPointer {}
+
+/// The storage the inputs of a bound [`PullNode`] read from.
+///
+/// The pointers are cleared when the pipeline lets go of the node, so a source that
+/// outlives the pipeline panics instead of reading freed storage.
+struct Slots(Vec<Slot>);
+
+impl Drop for Slots {
+    fn drop(&mut self) {
+        for slot in self.0.iter() {
+            // Waits for any source still reading.
+            *slot.write().unwrap() = None;
+        }
+    }
+}
+
+/// What a [`PullNode`]'s node sees at the other end of an input: a node with a single
+/// output, whose source reads what the graph wrote to the input.
+struct Feed<T> {
+    slot: Slot,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Feed<T> {
+    /// Reads the chunk in the slot, keeping the pipeline from dropping it meanwhile.
+    fn with_chunk<R>(&self, read: impl FnOnce(&Chunk<T>) -> R) -> R {
+        let slot = self.slot.read().unwrap();
+        let pointer = slot
+            .as_ref()
+            .expect("pulled from a pull node's input after its pipeline was dropped");
+        read(cables_core::as_input::<Chunk<T>>(pointer.0))
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> Node for Feed<T> {
+    fn connect(&mut self, _: SocketIdx, _: NodeRef, _: SocketIdx) -> Result<(), SocketConnectionError> {
+        Err(SocketConnectionError::NoSuchSocket)
+    }
+
+    fn disconnect(&mut self, _: SocketIdx) {}
+
+    fn as_source(&mut self, from_out_socket: SocketIdx) -> Result<Box<dyn Any>, SocketConnectionError> {
+        if from_out_socket != 0 {
+            return Err(SocketConnectionError::NoSuchSocket);
+        }
+        let source: Box<dyn Source<Item = T>> = Box::new(Feed::<T> {
+            slot: self.slot.clone(),
+            marker: PhantomData,
+        });
+        Ok(Box::new(source))
+    }
+}
+
+impl<T: Clone + 'static> Source for Feed<T> {
+    type Item = T;
+
+    fn pull(&mut self) -> Option<T> {
+        self.with_chunk(|chunk| chunk.item.clone())
+    }
+
+    fn info(&self) -> StreamInfo {
+        self.with_chunk(|chunk| chunk.info)
+    }
+}
+
+/// Where a sink's drain receives its inputs' chunks from.
+///
+/// Every run, the pipeline sends the chunks of all of the sink's inputs at once. A sink
+/// that drains its inputs one after another never leaves the pipeline waiting on an input
+/// it isn't reading yet: the chunks of the other inputs queue up here instead.
+#[derive(Default)]
+struct Inbox {
+    /// The channel of every pipeline the sink was bound to, oldest first,
+    /// along with the generation of the binding.
+    channels: Mutex<VecDeque<(u64, Receiver<Vec<AnyChunk>>)>>,
+    next_generation: AtomicU64,
+    /// Only used by the drain's thread.
+    reader: Mutex<InboxReader>,
+}
+
+#[derive(Default)]
+struct InboxReader {
+    /// The channel the drain is receiving from.
+    channel: Option<Receiver<Vec<AnyChunk>>>,
+    /// Chunks that were received but not pulled yet, for each input.
+    queued: Vec<VecDeque<AnyChunk>>,
+}
+
+impl Inbox {
+    /// Opens a channel for a new binding of the sink.
+    ///
+    /// Returns the generation of the binding, and the channel's sender.
+    fn open(&self) -> (u64, SyncSender<Vec<AnyChunk>>) {
+        let (sender, receiver) = mpsc::sync_channel(SINK_BUFFER_BLOCKS);
+        let generation = self.next_generation.fetch_add(1, Ordering::Relaxed);
+        self.channels.lock().unwrap().push_back((generation, receiver));
+        (generation, sender)
+    }
+
+    /// Forgets everything bindings before `generation` sent, for a new drain to start from scratch.
+    fn restart(&self, generation: u64) {
+        self.channels
+            .lock()
+            .unwrap()
+            .retain(|(channel_generation, _)| *channel_generation >= generation);
+        *self.reader.lock().unwrap() = InboxReader::default();
+    }
+
+    /// Drops every channel, so the pipelines sending to the sink stop instead of waiting for it.
+    fn close(&self) {
+        self.channels.lock().unwrap().clear();
+        *self.reader.lock().unwrap() = InboxReader::default();
+    }
+
+    /// Receives the next chunk of an input, or `None` once every pipeline the sink was bound to hung up.
+    fn receive(&self, input: usize) -> Option<AnyChunk> {
+        let mut reader = self.reader.lock().unwrap();
+        loop {
+            if let Some(chunk) = reader.queued.get_mut(input).and_then(VecDeque::pop_front) {
+                return Some(chunk);
+            }
+            if reader.channel.is_none() {
+                // Only lock the channels for this long, so binding the sink never waits on a drain.
+                reader.channel = Some(self.channels.lock().unwrap().pop_front()?.1);
+            }
+            match reader.channel.as_ref().unwrap().recv() {
+                Ok(block) => {
+                    reader.queued.resize_with(block.len(), VecDeque::new);
+                    for (queue, chunk) in reader.queued.iter_mut().zip(block) {
+                        queue.push_back(chunk);
+                    }
+                }
+                Err(_) => reader.channel = None,
+            }
+        }
+    }
+}
+
+/// Closes a sink's [`Inbox`] when its drain's thread exits, however it does.
+struct HangUp(Arc<Inbox>);
+
+impl Drop for HangUp {
+    fn drop(&mut self) {
+        self.0.close();
+    }
+}
+
+/// What a sink sees at the other end of an input: a node with a single output,
+/// whose source receives a chunk every time the pipeline runs.
+struct ChannelFeed<T> {
+    inbox: Arc<Inbox>,
+    input: usize,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T: Clone + Send + Sync + 'static> Node for ChannelFeed<T> {
+    fn connect(&mut self, _: SocketIdx, _: NodeRef, _: SocketIdx) -> Result<(), SocketConnectionError> {
+        Err(SocketConnectionError::NoSuchSocket)
+    }
+
+    fn disconnect(&mut self, _: SocketIdx) {}
+
+    fn as_source(&mut self, from_out_socket: SocketIdx) -> Result<Box<dyn Any>, SocketConnectionError> {
+        if from_out_socket != 0 {
+            return Err(SocketConnectionError::NoSuchSocket);
+        }
+        let source: Box<dyn Source<Item = T>> = Box::new(ChannelSource {
+            inbox: self.inbox.clone(),
+            input: self.input,
+            next: RefCell::new(None),
+        });
+        Ok(Box::new(source))
+    }
+}
+
+struct ChannelSource<T> {
+    inbox: Arc<Inbox>,
+    input: usize,
+    /// A chunk that was received early, to learn the stream's info before pulling.
+    next: RefCell<Option<Chunk<T>>>,
+}
+
+impl<T: 'static> ChannelSource<T> {
+    fn receive(&self) {
+        let mut next = self.next.borrow_mut();
+        if next.is_none() {
+            *next = self.inbox.receive(self.input).map(|chunk| {
+                *chunk
+                    .downcast::<Chunk<T>>()
+                    .expect("a sink's input received a chunk of another type")
+            });
+        }
+    }
+}
+
+impl<T: Send + 'static> Source for ChannelSource<T> {
+    type Item = T;
+
+    fn pull(&mut self) -> Option<T> {
+        self.receive();
+        self.next.get_mut().take()?.item
+    }
+
+    fn info(&self) -> StreamInfo {
+        self.receive();
+        self.next.borrow().as_ref().map(|chunk| chunk.info).unwrap_or_default()
+    }
+}
@@ -5,8 +5,20 @@
 //! If this description sounds generic, it's because productions can do all sorts of things.
 
 use std::sync::{Arc, RwLock, Weak};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
 use {std::any::Any};
-use crate::IOError;
+use cables_core::document::{DocumentError, GraphDocument};
+use std::pin::Pin;
+use std::task::Context;
+use cables_core::graph::{CompileError, ConnectError, Graph, GraphPipeline, NodeKey, ProcessContext, SocketData};
+use cables_core::registry::{NodeRegistry, Parameters, RegistryError};
+use crate::task::{Poll, Progress, Task};
+use crate::OvertoneError;
+
+mod adapter;
+pub use adapter::{Chunk, PullNode};
 
 pub type SocketIdx = usize;
 pub type FormatName = String;
@@ -15,15 +27,251 @@ pub type NodeWeakRef = Weak<RwLock<dyn Node>>;
 
 /// The main struct of this module.
 ///
-/// This is a graph that contains interconnected production_graph.
+/// This is a graph that contains interconnected production nodes.
+/// It runs on [`cables_core`], so any [`cables_core::graph::Node`] can be part of it,
+/// and pull-model [`Node`]s can join through a [`PullNode`].
+///
+/// A project keeps its production setups in its `productions/` directory,
+/// one `<name>.toml` file each.
 pub struct ProductionSetup {
-    nodes: Vec<NodeRef>
+    /// The name of the setup, which is also the name of the file it's saved as.
+    pub name: String,
+    graph: Graph,
+    /// The [format names](Value::get_format_name) of the types sockets carry,
+    /// by [type name](SocketData::type_name).
+    formats: HashMap<&'static str, FormatName>,
+}
+
+impl ProductionSetup {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            graph: Graph::new(),
+            formats: HashMap::new(),
+        }
+    }
+
+    /// The graph of this production setup.
+    pub fn graph(&self) -> &Graph {
+        &self.graph
+    }
+
+    pub fn graph_mut(&mut self) -> &mut Graph {
+        &mut self.graph
+    }
+
+    /// Lets this setup know the format of sockets carrying `T`,
+    /// so connection errors and user interfaces can talk about formats instead of types.
+    pub fn register_format<T: Value + Default + 'static>(&mut self) {
+        self.formats
+            .insert(std::any::type_name::<T>(), T::default().get_format_name());
+    }
+
+    /// The format of the values a socket carries, if it was registered.
+    pub fn format_of(&self, socket: &SocketData) -> Option<&FormatName> {
+        self.formats.get(socket.type_name())
+    }
+
+    /// Adds a node of a type contributed by a plugin (or built into [`cables_core`]).
+    ///
+    /// Parameters missing from `parameters` take their default value.
+    pub fn add_node(&mut self, registry: &NodeRegistry, type_id: &str, parameters: &Parameters) -> Result<NodeKey, ProductionError> {
+        let node = registry.create(type_id, parameters).map_err(ProductionError::Registry)?;
+        Ok(self.graph.insert_boxed(node))
+    }
+
+    /// Adds a node that wasn't contributed by a plugin, like a [`PullNode`].
+    ///
+    /// Nodes whose type isn't registered can't be saved.
+    pub fn insert<N: cables_core::graph::Node>(&mut self, node: N) -> NodeKey {
+        self.graph.insert(node)
+    }
+
+    /// Removes a node and all of its connections.
+    pub fn remove_node(&mut self, node: NodeKey) -> Option<Box<dyn cables_core::graph::Node>> {
+        self.graph.remove(node)
+    }
+
+    /// Connects an output socket to an input socket, if they carry the same format.
+    pub fn connect<'a>(
+        &mut self,
+        output_node: NodeKey,
+        output_socket: impl Into<cables_core::graph::SocketRef<'a>>,
+        input_node: NodeKey,
+        input_socket: impl Into<cables_core::graph::SocketRef<'a>>,
+    ) -> Result<(), ProductionError> {
+        self.graph
+            .connect(output_node, output_socket, input_node, input_socket)
+            .map_err(|error| match error {
+                ConnectError::TypeMismatch { output_type, input_type } => {
+                    match (self.formats.get(output_type), self.formats.get(input_type)) {
+                        (Some(output), Some(input)) => ProductionError::FormatMismatch {
+                            output: output.clone(),
+                            input: input.clone(),
+                        },
+                        _ => ProductionError::Connect(error),
+                    }
+                }
+                error => ProductionError::Connect(error),
+            })
+    }
+
+    /// Disconnects whatever is connected to an input socket.
+    pub fn disconnect<'a>(&mut self, input_node: NodeKey, input_socket: impl Into<cables_core::graph::SocketRef<'a>>) {
+        self.graph.disconnect(input_node, input_socket);
+    }
+
+    /// Returns a task that runs the whole setup until every sink is drained.
+    ///
+    /// Each poll of the task runs the pipeline once, pushing a chunk to every
+    /// [`PullNode`] sink, until their streams end. Sinks stream their input
+    /// instead of holding it all in memory. A setup without such sinks runs once.
+    pub fn drain(&self) -> Result<DrainTask<'_>, ProductionError> {
+        let sinks: Vec<&PullNode> = self
+            .graph
+            .nodes()
+            .filter_map(|(_, node)| {
+                let node: &dyn Any = node;
+                node.downcast_ref::<PullNode>().filter(|node| node.is_sink())
+            })
+            .collect();
+        let pipeline = self.graph.compile_all().map_err(ProductionError::Compile)?;
+
+        Ok(DrainTask {
+            drained: vec![false; sinks.len()],
+            sinks,
+            pipeline: Some(pipeline),
+            context: ProcessContext::default(),
+            progress: Progress::new(),
+        })
+    }
+
+    /// Loads a production setup from a `<name>.toml` file.
+    ///
+    /// The registry must know the types of all of the setup's nodes.
+    pub fn load_from_file<P: AsRef<Path>>(path: P, registry: &NodeRegistry) -> Result<Self, ProductionError> {
+        let path = path.as_ref();
+        let raw = fs::read_to_string(path).map_err(ProductionError::IO)?;
+        let document: GraphDocument = toml::from_str(&raw).map_err(ProductionError::Deserialize)?;
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let mut setup = Self::new(name);
+        setup.graph = document.to_graph(registry).map_err(ProductionError::Document)?;
+        Ok(setup)
+    }
+
+    /// Saves this production setup to a file.
+    ///
+    /// The registry must know the types of all of the setup's nodes.
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P, registry: &NodeRegistry) -> Result<(), ProductionError> {
+        let document = GraphDocument::from_graph(&self.graph, registry).map_err(ProductionError::Document)?;
+        let raw = toml::to_string_pretty(&document).map_err(ProductionError::Serialize)?;
+        fs::write(path, raw).map_err(ProductionError::IO)
+    }
 }
 
-/// An element of a production setup.
+/// Drains the sinks of a [`ProductionSetup`], one run of its pipeline per poll.
+///
+/// Its progress is the average progress of the sinks. Cancelling it cancels them.
+/// As soon as a sink fails, the others are cancelled and the task returns its error.
+pub struct DrainTask<'a> {
+    sinks: Vec<&'a PullNode>,
+    /// Whether each sink's drain is over and was collected.
+    drained: Vec<bool>,
+    /// Dropped when the task finishes, which lets the sinks see their streams end.
+    pipeline: Option<GraphPipeline>,
+    context: ProcessContext,
+    progress: Progress,
+}
+
+impl DrainTask<'_> {
+    fn cancel_sinks(&self) {
+        for sink in self.sinks.iter() {
+            if let Some(progress) = sink.progress() {
+                progress.cancel();
+            }
+        }
+    }
+
+    /// Collects the sinks whose drain is over, returning the error of the first one that failed.
+    fn collect_drained(&mut self) -> Result<(), ExportError> {
+        for (sink, drained) in self.sinks.iter().zip(self.drained.iter_mut()) {
+            if !*drained && sink.has_finished_draining() {
+                *drained = true;
+                sink.wait_for_drain()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), ProductionError> {
+        self.pipeline = None;
+        let mut result = Ok(());
+        for sink in self.sinks.iter() {
+            // Wait for every sink, even after one fails, so none is left draining.
+            let drained = sink.wait_for_drain().map_err(ProductionError::Export);
+            result = result.and(drained);
+        }
+        if result.is_ok() {
+            self.progress.set(1.0);
+        }
+        result
+    }
+}
+
+impl Task for DrainTask<'_> {
+    type Output = Result<(), ProductionError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let Some(pipeline) = this.pipeline.as_mut() else {
+            panic!("polled a drain task after it finished");
+        };
+
+        if this.progress.is_cancelled() {
+            this.cancel_sinks();
+            return Poll::Ready(this.finish().and(Err(ProductionError::Export(ExportError::Cancelled))));
+        }
+
+        pipeline.run(&this.context);
+        this.context.advance();
+
+        let sink_progress: f32 = this
+            .sinks
+            .iter()
+            .filter_map(|sink| sink.progress())
+            .map(|progress| progress.get())
+            .sum();
+        this.progress.set(sink_progress / this.sinks.len().max(1) as f32);
+
+        if let Err(error) = this.collect_drained() {
+            this.cancel_sinks();
+            // The other sinks are most likely cancelled, the first error is the one that matters.
+            let _ = this.finish();
+            return Poll::Ready(Err(ProductionError::Export(error)));
+        }
+        let ended = this.sinks.iter().zip(&this.drained).all(|(sink, drained)| *drained || sink.has_ended());
+        if ended {
+            return Poll::Ready(this.finish());
+        }
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+
+    fn progress(&self) -> Option<&Progress> {
+        Some(&self.progress)
+    }
+}
+
+/// An element of a pull-model graph.
 /// These can be connected with one another,
 /// forming a DAG.
-pub trait Node {
+///
+/// To use one in a [`ProductionSetup`], wrap it in a [`PullNode`].
+pub trait Node: Send + Sync {
     /// Attempts to connect an incoming signal to one of this node's sockets.
     fn connect(&mut self, to_socket: SocketIdx, from_node: NodeRef, from_socket: SocketIdx) -> Result<(), SocketConnectionError>;
     /// Disconnects whatever is connected to the socket at the given index.
@@ -46,13 +294,50 @@ impl dyn Node {
     }
 }
 
-pub trait Source {
+/// A stream of items that are pulled one chunk at a time.
+pub trait Source: Send {
     type Item;
-    fn pull(&mut self) -> Self::Item;
+
+    /// Pulls the next chunk of the stream, or `None` once the stream has ended.
+    ///
+    /// Chunks should be small, so a stream never has to be in memory all at once.
+    fn pull(&mut self) -> Option<Self::Item>;
+
+    /// Describes the stream.
+    fn info(&self) -> StreamInfo;
+}
+
+/// What a [`Source`] streams, besides its items.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StreamInfo {
+    /// How many frames the stream has per second.
+    pub sample_rate: u32,
+    /// How many samples there are in each frame.
+    pub channels: u16,
+    /// How many frames the stream has in total, if that's known.
+    pub length: Option<u64>,
+}
+
+impl Default for StreamInfo {
+    /// Mono, at 44.1kHz, of unknown length.
+    fn default() -> Self {
+        Self {
+            sample_rate: 44100,
+            channels: 1,
+            length: None,
+        }
+    }
 }
 
 pub struct SocketRef(pub NodeRef, pub SocketIdx);
 
+impl SocketRef {
+    /// Gets a source of `Item`s from the output socket this refers to.
+    pub fn source<Item: 'static>(&self) -> Result<Box<dyn Source<Item = Item>>, SocketConnectionError> {
+        self.0.write().unwrap().try_get_source(self.1)
+    }
+}
+
 #[derive(Debug)]
 pub enum SocketConnectionError {
     /// No socket at the given index.
@@ -93,11 +378,62 @@ pub trait Value {
 pub trait Sink: Node {
     /// Exhausts the graph by pulling values
     /// and exports them to possibly a file.
-    fn drain(&mut self) -> Result<(), ExportError>;
+    ///
+    /// While draining, the sink should report how far along it is through `progress`,
+    /// and stop with [`ExportError::Cancelled`] if `progress` gets cancelled.
+    fn drain(&mut self, progress: &Progress) -> Result<(), ExportError>;
 }
 
 /// An error that occurred during an export.
 #[derive(Debug)]
 pub enum ExportError {
+    /// Nothing is connected to this input socket of the sink.
+    UnconnectedInput(SocketIdx),
+    /// The node connected to this input socket doesn't stream the format the sink needs.
+    FormatMismatch(SocketIdx),
+    /// The node connected to this input socket couldn't give the sink a source.
+    Upstream {
+        socket: SocketIdx,
+        error: SocketConnectionError,
+    },
+    /// The export was cancelled before it finished.
+    Cancelled,
     IO(std::io::Error),
+}
+
+impl ExportError {
+    /// Gets the source connected to a sink's input socket, describing what went wrong if it can't.
+    pub fn source<Item: 'static>(input: Option<&SocketRef>, socket: SocketIdx) -> Result<Box<dyn Source<Item = Item>>, ExportError> {
+        let input = input.ok_or(ExportError::UnconnectedInput(socket))?;
+        input.source().map_err(|error| match error {
+            SocketConnectionError::IncorrectFormat => ExportError::FormatMismatch(socket),
+            error => ExportError::Upstream { socket, error },
+        })
+    }
+}
+
+/// An error that occurred while editing, running or saving a [`ProductionSetup`].
+#[derive(Debug)]
+pub enum ProductionError {
+    /// A node couldn't be created from the registry.
+    Registry(RegistryError),
+    /// Two sockets couldn't be connected.
+    Connect(ConnectError),
+    /// An output socket was connected to an input socket of another format.
+    FormatMismatch { output: FormatName, input: FormatName },
+    /// The setup's graph couldn't be compiled, so it can't be drained.
+    Compile(CompileError),
+    /// A sink failed to drain.
+    Export(ExportError),
+    /// The setup couldn't be converted to or from its saved form.
+    Document(DocumentError),
+    Serialize(toml::ser::Error),
+    Deserialize(toml::de::Error),
+    IO(std::io::Error),
+}
+
+impl From<ProductionError> for OvertoneError {
+    fn from(value: ProductionError) -> Self {
+        OvertoneError::ProductionError(value)
+    }
 }
\ No newline at end of file
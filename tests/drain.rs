@@ -0,0 +1,111 @@
+use overtone::task::{self, Progress};
+use overtone::transformer::{
+    ExportError, Node, NodeRef, ProductionError, ProductionSetup, PullNode, Sink,
+    SocketConnectionError, SocketIdx, SocketRef, Source, StreamInfo,
+};
+use std::any::Any;
+use std::sync::{Arc, RwLock};
+
+fn new_node<N: Node + 'static>(node: N) -> NodeRef {
+    Arc::new(RwLock::new(node))
+}
+
+/// Streams ones, forever.
+struct Ones;
+
+impl Node for Ones {
+    fn connect(&mut self, _: SocketIdx, _: NodeRef, _: SocketIdx) -> Result<(), SocketConnectionError> {
+        Err(SocketConnectionError::NoSuchSocket)
+    }
+
+    fn disconnect(&mut self, _: SocketIdx) {}
+
+    fn as_source(&mut self, from_out_socket: SocketIdx) -> Result<Box<dyn Any>, SocketConnectionError> {
+        if from_out_socket != 0 {
+            return Err(SocketConnectionError::NoSuchSocket);
+        }
+        let source: Box<dyn Source<Item = f32>> = Box::new(Ones);
+        Ok(Box::new(source))
+    }
+}
+
+impl Source for Ones {
+    type Item = f32;
+
+    fn pull(&mut self) -> Option<f32> {
+        Some(1.0)
+    }
+
+    fn info(&self) -> StreamInfo {
+        StreamInfo::default()
+    }
+}
+
+/// Pulls until its stream ends or it's cancelled, or fails after pulling `fail_after` chunks.
+struct Drainer {
+    source: Option<SocketRef>,
+    fail_after: Option<usize>,
+}
+
+impl Drainer {
+    fn new(fail_after: Option<usize>) -> Self {
+        Self { source: None, fail_after }
+    }
+}
+
+impl Node for Drainer {
+    fn connect(&mut self, to_socket: SocketIdx, from_node: NodeRef, from_socket: SocketIdx) -> Result<(), SocketConnectionError> {
+        if to_socket != 0 {
+            return Err(SocketConnectionError::NoSuchSocket);
+        }
+        self.source = Some(SocketRef(from_node, from_socket));
+        Ok(())
+    }
+
+    fn disconnect(&mut self, _: SocketIdx) {
+        self.source = None;
+    }
+
+    fn as_source(&mut self, _: SocketIdx) -> Result<Box<dyn Any>, SocketConnectionError> {
+        Err(SocketConnectionError::NoSuchSocket)
+    }
+
+    fn as_sink(&mut self) -> Option<&mut dyn Sink> {
+        Some(self)
+    }
+}
+
+impl Sink for Drainer {
+    fn drain(&mut self, progress: &Progress) -> Result<(), ExportError> {
+        let mut source = ExportError::source::<f32>(self.source.as_ref(), 0)?;
+        let mut pulled = 0;
+        while source.pull().is_some() {
+            if progress.is_cancelled() {
+                return Err(ExportError::Cancelled);
+            }
+            if Some(pulled) == self.fail_after {
+                return Err(ExportError::IO(std::io::Error::other("disk full")));
+            }
+            pulled += 1;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn failing_sink_fails_the_drain() {
+    let mut setup = ProductionSetup::new("Failing");
+
+    let ones = setup.insert(PullNode::new(new_node(Ones)).output::<f32>("out"));
+    let failing = setup.insert(PullNode::new(new_node(Drainer::new(Some(3)))).input::<f32>("in"));
+    // Never ends by itself, so the drain only ends because the other sink failed.
+    let endless = setup.insert(PullNode::new(new_node(Drainer::new(None))).input::<f32>("in"));
+    setup.connect(ones, "out", failing, "in").unwrap();
+    setup.connect(ones, "out", endless, "in").unwrap();
+
+    let result = task::block_on(setup.drain().unwrap());
+    assert!(
+        matches!(result, Err(ProductionError::Export(ExportError::IO(_)))),
+        "{result:?}"
+    );
+}